@@ -1,51 +1,91 @@
-use crate::conway::{Grid, CELL_SIZE, GRID_HEIGHT, GRID_WIDTH};
-use crate::soundgen::SoundGenerator;
-use core::time::Duration;
-use std::future::Future;
+use super::transport::DIVISIONS;
+use crate::conway::{GenerationDelta, Grid, Index, PatternFormat, CELL_SIZE};
+use strum::IntoEnumIterator;
 use wasm_bindgen::{JsCast, JsValue};
-use wasm_bindgen_futures::spawn_local;
-use wasm_timer::Delay;
 use web_sys::HtmlCanvasElement;
 use yew::prelude::*;
+use yew::services::reader::File;
 use yew::MouseEvent;
 
-pub fn send_future<COMP: Component, F>(link: ComponentLink<COMP>, future: F)
-where
-    F: Future<Output = COMP::Message> + 'static,
-{
-    spawn_local(async move {
-        link.send_message(future.await);
-    });
-}
-
 #[derive(Properties, Clone)]
 pub struct GridProps {
     pub on_delete: Callback<MouseEvent>,
+    pub on_edit: Callback<Edit>,
+    /// Called with the pattern file the user picked, the `App` reads it
+    pub on_load_pattern: Callback<File>,
     pub grid: Grid,
+    /// Cells that changed since the last render, the whole grid is repainted without it
+    #[prop_or_default]
+    pub delta: Option<GenerationDelta>,
+    /// Whether the grid changed since the last render, the unchanged grids aren't repainted
+    #[prop_or(true)]
+    pub changed: bool,
+    /// Number of beats between the generations of the grid
+    pub division: u32,
+    /// Number of organisms in the current generation of the grid
+    pub organisms: usize,
 }
+
+/// Draws a grid owned by the `App`
 pub struct GridView {
     link: ComponentLink<Self>,
     props: GridProps,
     canvas_ref: NodeRef,
     ctx: Option<web_sys::CanvasRenderingContext2d>,
-    pub stopped: bool,
 }
 
 impl GridView {
+    fn pattern_buttons(&self) -> Html {
+        let on_edit = self.props.on_edit.clone();
+        let export_pattern = Callback::from(move |e: ChangeData| {
+            if let ChangeData::Select(select) = e {
+                if let Ok(format) = select.value().parse() {
+                    on_edit.emit(Edit::ExportPattern(format));
+                }
+                // back to the placeholder, so the same format can be picked again
+                select.set_selected_index(0);
+            }
+        });
+        let load_pattern = self.props.on_load_pattern.clone();
+        let load_pattern = Callback::from(move |e: ChangeData| {
+            if let ChangeData::Files(files) = e {
+                if let Some(file) = files.get(0) {
+                    load_pattern.emit(file);
+                }
+            }
+        });
+        html! {
+            <>
+                <label class="button grid__import">
+                    <i class="fas fa-file-import"></i>
+                    <input type="file" accept=".rle,.cells,.lif,.life" onchange=load_pattern />
+                </label>
+                <select class="grid__export" onchange=export_pattern>
+                    <option value="" selected=true disabled=true>{"Export"}</option>
+                    {PatternFormat::iter().map(|format| html! {
+                        <option value=format.to_string()>{format.to_string()}</option>
+                    }).collect::<Html>()}
+                </select>
+            </>
+        }
+    }
+
     fn draw_board(&self) {
         let ctx = self.ctx.as_ref().unwrap();
         ctx.begin_path();
         ctx.set_stroke_style(&JsValue::from_str("#989e9e"));
         ctx.set_line_width(2.0);
+        let width = self.props.grid.width();
+        let height = self.props.grid.height();
         // +1 because we need that extra lines to close up the grid
-        for x in 0..GRID_WIDTH + 1 {
+        for x in 0..width + 1 {
             ctx.move_to((x * CELL_SIZE) as f64, 0.0);
-            ctx.line_to((x * CELL_SIZE) as f64, (GRID_HEIGHT * CELL_SIZE) as f64);
+            ctx.line_to((x * CELL_SIZE) as f64, (height * CELL_SIZE) as f64);
         }
 
-        for y in 0..GRID_HEIGHT + 1 {
+        for y in 0..height + 1 {
             ctx.move_to(0.0, (y * CELL_SIZE) as f64);
-            ctx.line_to((GRID_HEIGHT * CELL_SIZE) as f64, (y * CELL_SIZE) as f64);
+            ctx.line_to((width * CELL_SIZE) as f64, (y * CELL_SIZE) as f64);
         }
 
         ctx.stroke();
@@ -54,10 +94,11 @@ impl GridView {
     fn draw_cells(&self) {
         let ctx = self.ctx.as_ref().unwrap();
         ctx.begin_path();
+        let width = self.props.grid.width();
         for (i, c) in self.props.grid.iter().enumerate() {
-            let y = ((i / GRID_WIDTH) as f32).ceil() as usize;
-            let x = i - (y * GRID_WIDTH);
-            ctx.set_fill_style(&JsValue::from_str(c.color()));
+            let y = i / width;
+            let x = i % width;
+            ctx.set_fill_style(&JsValue::from_str(&c.color()));
             ctx.fill_rect(
                 (x * CELL_SIZE) as f64,
                 (y * CELL_SIZE) as f64,
@@ -72,51 +113,77 @@ impl GridView {
         self.draw_board();
     }
 
-    pub fn toggle_cell(&mut self, event: &MouseEvent) {
+    /// Repaints only the cells that changed in the last generation and the cells whose color
+    /// depends on their age, leaving the lines of the board intact
+    fn draw_delta(&self, delta: &GenerationDelta) {
+        let ctx = self.ctx.as_ref().unwrap();
+        let width = self.props.grid.width();
+
+        for index in repainted_cells(&self.props.grid, delta) {
+            let cell = self.props.grid.get_cell(index.to_offset(width)).unwrap();
+            ctx.set_fill_style(&JsValue::from_str(&cell.color()));
+            ctx.fill_rect(
+                (index.col * CELL_SIZE) as f64 + 1.0,
+                (index.row * CELL_SIZE) as f64 + 1.0,
+                CELL_SIZE as f64 - 2.0,
+                CELL_SIZE as f64 - 2.0,
+            );
+        }
+    }
+
+    /// Offset of the cell under the cursor, if there is one
+    fn cell_at(&self, event: &MouseEvent) -> Option<usize> {
         let x = (event.offset_x() as f32 / CELL_SIZE as f32).floor();
         let y = (event.offset_y() as f32 / CELL_SIZE as f32).floor();
-        let index = x as usize + (y as usize * GRID_WIDTH);
-        log::info!("x:{} y:{} i:{}", x, y, index);
-        // TODO: handle this
-        let alive = self
-            .props
-            .grid
-            .get_cell(index)
-            .map(|cell| cell.alive)
-            .expect("This shouldn't happen");
-
-        self.props.grid.set_cell(index, !alive);
-        self.draw();
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if x >= self.props.grid.width() || y >= self.props.grid.height() {
+            return None;
+        }
+        log::info!("x:{} y:{}", x, y);
+        Some(x + y * self.props.grid.width())
     }
+}
 
-    pub fn simulate(&mut self) {
-        if !self.props.grid.stopped {
-            self.props.grid.start();
-            let soundgen = SoundGenerator::new();
-            self.props.grid.next_gen();
-            self.draw();
+/// Cells to repaint after the generation of the `delta`: the cells that died or changed their
+/// dying state, and the alive cells whose color changed with their age. The cells that were born
+/// are alive, so they are among the latter.
+fn repainted_cells(grid: &Grid, delta: &GenerationDelta) -> Vec<Index> {
+    let recolored = grid
+        .iter()
+        .enumerate()
+        .filter(|(_, cell)| cell.is_recolored())
+        .map(|(idx, _)| Index::from_offset(idx, grid.width()));
 
-            for (x, y) in self.props.grid.get_pitch_and_volume_per_subgrid() {
-                soundgen.play(*x as u32).expect("Fix it");
-            }
+    delta
+        .died
+        .iter()
+        .chain(delta.decayed.iter())
+        .copied()
+        .chain(recolored)
+        .collect()
+}
 
-            let wait = Delay::new(Duration::from_millis(300));
-            // TODO: handle this err
-            let future = async {
-                match wait.await {
-                    Ok(md) => Message::Simulate,
-                    Err(err) => Message::Simulate,
-                }
-            };
-            send_future(self.link.clone(), future);
-        }
-    }
+/// Changes of a grid requested by its view, the grids are changed only by the `App`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    ToggleCell(usize),
+    ToggleSimulation,
+    FastForward,
+    Seek(u64),
+    Undo,
+    Redo,
+    SetDivision(u32),
+    ToggleUnbounded,
+    CenterView,
+    /// Saves the alive cells as a file of the format
+    ExportPattern(PatternFormat),
 }
 
 pub enum Message {
     ClickCanvas(MouseEvent),
-    Simulate,
-    ToggleSimulation,
 }
 
 impl Component for GridView {
@@ -129,36 +196,41 @@ impl Component for GridView {
             canvas_ref: NodeRef::default(),
             ctx: None,
             link,
-            stopped: true,
         }
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        false
+        self.props = props;
+        true
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Message::ClickCanvas(e) => {
-                self.toggle_cell(&e);
-                false
-            }
-            Message::Simulate => {
-                self.simulate();
+                if let Some(offset) = self.cell_at(&e) {
+                    self.props.on_edit.emit(Edit::ToggleCell(offset));
+                }
                 false
             }
-            Message::ToggleSimulation => {
-                self.props.grid.toggle();
-                self.simulate();
-                true
-            }
         }
     }
 
     fn view(&self) -> Html {
         let delete_grid = &self.props.on_delete;
         let click_canvas = self.link.callback(|e| Message::ClickCanvas(e));
-        let toggle_simulation = self.link.callback(|_| Message::ToggleSimulation);
+        let on_edit = &self.props.on_edit;
+        let toggle_simulation = on_edit.reform(|_| Edit::ToggleSimulation);
+        let fast_forward = on_edit.reform(|_| Edit::FastForward);
+        let undo = on_edit.reform(|_| Edit::Undo);
+        let redo = on_edit.reform(|_| Edit::Redo);
+        let toggle_unbounded = on_edit.reform(|_| Edit::ToggleUnbounded);
+        let center_view = on_edit.reform(|_| Edit::CenterView);
+        let seek = on_edit.reform(|e: InputData| Edit::Seek(e.value.parse().unwrap_or_default()));
+        let set_division = on_edit.reform(|e: ChangeData| match e {
+            ChangeData::Select(select) => Edit::SetDivision(select.value().parse().unwrap_or(1)),
+            _ => Edit::SetDivision(1),
+        });
+        let grid = &self.props.grid;
         html! {
             <div class="grid">
                 <div class="grid__controls">
@@ -172,8 +244,57 @@ impl Component for GridView {
                             html!{ <i class="fas fa-stop"></i> }
                         }}
                     </button>
+                    <button class="button grid__forward" onclick=fast_forward>
+                        <i class="fas fa-forward"></i>
+                    </button>
+                    <button class="button grid__undo" onclick=undo>
+                        <i class="fas fa-undo"></i>
+                    </button>
+                    <button class="button grid__redo" onclick=redo>
+                        <i class="fas fa-redo"></i>
+                    </button>
+                    <button class="button grid__unbounded" onclick=toggle_unbounded>
+                        {if grid.is_unbounded() {
+                            html! { <i class="fas fa-compress-arrows-alt"></i> }
+                        } else {
+                            html! { <i class="fas fa-expand-arrows-alt"></i> }
+                        }}
+                    </button>
+                    {if grid.is_unbounded() {
+                        html! {
+                            <button class="button grid__center" onclick=center_view>
+                                <i class="fas fa-crosshairs"></i>
+                            </button>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    {self.pattern_buttons()}
+                    <select class="grid__division" onchange=set_division>
+                        {DIVISIONS.iter().map(|&division| html! {
+                            <option value=division.to_string() selected=division == self.props.division>
+                                {format!("1/{}", division)}
+                            </option>
+                        }).collect::<Html>()}
+                    </select>
+                    {if let Some(soup) = self.props.grid.soup() {
+                        html! { <span class="grid__seed">{format!("seed {}", soup.seed)}</span> }
+                    } else {
+                        html! {}
+                    }}
+                    <span class="grid__organisms">
+                        {format!("{} organisms", self.props.organisms)}
+                    </span>
                 </div>
                 <canvas onclick=click_canvas id="canvas" ref=self.canvas_ref.clone() class="grid__cells" />
+                <input
+                    class="grid__timeline"
+                    type="range"
+                    min=grid.oldest_generation().to_string()
+                    max=grid.newest_generation().to_string()
+                    value=grid.generation().to_string()
+                    oninput=seek
+                />
             </div>
         }
     }
@@ -181,8 +302,8 @@ impl Component for GridView {
     fn rendered(&mut self, first_render: bool) {
         if first_render {
             if let Some(canvas) = self.canvas_ref.cast::<HtmlCanvasElement>() {
-                canvas.set_height((GRID_HEIGHT * CELL_SIZE) as u32);
-                canvas.set_width((GRID_WIDTH * CELL_SIZE) as u32);
+                canvas.set_height((self.props.grid.height() * CELL_SIZE) as u32);
+                canvas.set_width((self.props.grid.width() * CELL_SIZE) as u32);
                 self.ctx = canvas
                     .get_context("2d")
                     .unwrap()
@@ -191,6 +312,53 @@ impl Component for GridView {
                     .ok();
                 self.draw();
             }
+        } else if let Some(delta) = &self.props.delta {
+            self.draw_delta(delta);
+        } else if self.props.changed {
+            self.draw();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conway::Rule;
+
+    #[test]
+    fn fully_decayed_cells_are_repainted() {
+        let mut grid = Grid::empty_with_size(6, 6);
+        grid.set_rule(Rule::brians_brain());
+        grid.set_cell(0usize, true).unwrap();
+        grid.start();
+
+        let delta = grid.next_gen_with_delta().unwrap();
+        assert!(repainted_cells(&grid, &delta).contains(&Index::new(0, 0)));
+
+        // the dying cell is dead now, without dying in this generation
+        let delta = grid.next_gen_with_delta().unwrap();
+        assert!(delta.is_empty());
+        assert_eq!(grid.get_cell(0usize).unwrap().decay, 0);
+        assert_eq!(repainted_cells(&grid, &delta), vec![Index::new(0, 0)]);
+    }
+
+    #[test]
+    fn old_cells_are_not_repainted() {
+        let mut grid = Grid::empty_with_size(6, 6);
+        for &(row, col) in &[(1, 1), (1, 2), (2, 1), (2, 2)] {
+            grid.set_cell(Index::new(row, col).to_offset(6), true)
+                .unwrap();
+        }
+        grid.start();
+
+        let delta = grid.next_gen_with_delta().unwrap();
+        assert_eq!(repainted_cells(&grid, &delta).len(), 4);
+
+        // the block keeps its color once its cells are old enough
+        for _ in 0..40 {
+            grid.next_gen_with_delta().unwrap();
         }
+        let delta = grid.next_gen_with_delta().unwrap();
+        assert!(repainted_cells(&grid, &delta).is_empty());
     }
 }
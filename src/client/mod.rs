@@ -1,8 +1,76 @@
 mod grid;
+mod transport;
 
-use crate::conway::{Conway, Grid};
-use grid::GridView;
+use crate::conway::{
+    library, Conway, Engine, GenerationDelta, Grid, GridId, HashLife, Index, OrganismTracker,
+    Pattern, PatternFormat, Policy, StabilityPolicy, Symmetry,
+};
+use crate::player::Strategy;
+use crate::soundgen::{
+    render_session, Note, Recording, Renderer, SampleFormat, Scale, ScaleKind, SoundGenerator,
+    Tuning, VelocityCurve, STANDARD_PITCH,
+};
+use grid::{Edit, GridView};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::time::Duration;
+use strum::IntoEnumIterator;
+use transport::{Control, Transport, MAX_BPM, MIN_BPM, TIME_SIGNATURES};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::spawn_local;
+use wasm_timer::Delay;
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, Url};
 use yew::prelude::*;
+use yew::services::reader::{File, FileData, ReaderService, ReaderTask};
+
+/// Number of generations skipped by the fast-forward button, as a power of two
+const FAST_FORWARD_LOG2: u8 = 10;
+
+/// Number of generations rendered into the downloaded audio
+const RENDERED_GENERATIONS: usize = 64;
+
+/// Time the browser has to start a download before its file is released
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub fn send_future<COMP: Component, F>(link: ComponentLink<COMP>, future: F)
+where
+    F: Future<Output = COMP::Message> + 'static,
+{
+    spawn_local(async move {
+        link.send_message(future.await);
+    });
+}
+
+/// Lets the user save the `bytes` as a file with the given name
+fn download(name: &str, mime: &str, bytes: &[u8]) -> Result<(), JsValue> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("no document"))?;
+    let anchor = document
+        .create_element("a")?
+        .dyn_into::<HtmlAnchorElement>()?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+
+    // the browser may start the download after the click returns
+    let wait = Delay::new(DOWNLOAD_TIMEOUT);
+    spawn_local(async move {
+        if let Err(err) = wait.await {
+            log::warn!("timer failed: {}", err);
+        }
+        if let Err(err) = Url::revoke_object_url(&url) {
+            log::warn!("can't release the download: {:?}", err);
+        }
+    });
+    Ok(())
+}
 
 struct State {
     simulation: Conway,
@@ -16,21 +84,733 @@ impl State {
     }
 }
 
+/// Tunings that can be picked without loading a Scala file
+const TUNINGS: [&str; 5] = ["12-EDO", "19-EDO", "24-EDO", "31-EDO", "Just"];
+
+/// Velocity curves that can be picked, by their names
+const VELOCITY_CURVES: [(&str, VelocityCurve); 3] = [
+    ("Clamped", VelocityCurve::Clamped { min: 0.2, max: 1.0 }),
+    ("Linear", VelocityCurve::Linear),
+    ("Log", VelocityCurve::Log),
+];
+
+/// Frequencies of the A above the middle C that can be picked
+const REFERENCE_PITCHES: [f32; 3] = [432.0, STANDARD_PITCH, 443.0];
+
+/// Returns the tuning with one of the names of `TUNINGS`
+fn tuning_preset(name: &str) -> Tuning {
+    match name.trim_end_matches("-EDO").parse() {
+        Ok(divisions) => Tuning::equal(divisions),
+        Err(_) if name == "Just" => Tuning::just(),
+        Err(_) => Tuning::default(),
+    }
+}
+
+/// Offset placing the pattern in the middle of the grid
+fn centered(grid: &Grid, pattern: &Pattern) -> Index {
+    Index::new(
+        grid.height().saturating_sub(pattern.height) / 2,
+        grid.width().saturating_sub(pattern.width) / 2,
+    )
+}
+
+/// Presets that are not in the pattern library
+const EMPTY_PRESET: &str = "Empty";
+const RANDOM_PRESET: &str = "Random";
+
 pub struct App {
     link: ComponentLink<Self>,
     state: State,
+    /// Name of the preset the new boards are spawned with
+    preset: String,
+    /// Engine the new boards are spawned with
+    engine: Engine,
+    /// Symmetry of the random boards
+    symmetry: Symmetry,
+    /// Fast-forwards the grids, the patterns it memoized speed up the next fast-forwards
+    hashlife: HashLife,
+    /// Cells that changed in the last tick, for the grids that evolved in it
+    deltas: Vec<(GridId, GenerationDelta)>,
+    /// Grids changed by the last tick, all the grids are repainted after the other messages
+    changed: Option<HashSet<GridId>>,
+    transport: Transport,
+    /// Number of beats between the generations of the grids, 1 if missing
+    divisions: HashMap<GridId, u32>,
+    /// Organisms of the grids, found again only when the grids change
+    trackers: HashMap<GridId, OrganismTracker>,
+    /// Identifies the scheduled tick, the ticks scheduled before a pause are ignored
+    clock: u64,
+    /// Order in which the subgrids are played
+    strategy: Strategy,
+    /// Scale the notes are chosen from
+    scale: Scale,
+    /// Kind of the scale from the catalogue, none for the scales typed by the user
+    scale_kind: Option<ScaleKind>,
+    /// Tuning the frequencies of the notes come from
+    tuning: Tuning,
+    /// Name of the tuning preset or of the loaded Scala file
+    tuning_name: String,
+    /// Maps the numbers of the births onto the loudness of the notes
+    velocity_curve: VelocityCurve,
+    /// Reads the loaded Scala file
+    reader: Option<ReaderTask>,
+    /// Reads the pattern files loaded into the grids
+    pattern_reader: Option<ReaderTask>,
+    /// Created with the first sound, browsers don't let the pages play before the user interacts
+    /// with them
+    soundgen: Option<SoundGenerator>,
+    /// Notes of the last recorded session
+    recording: Option<Recording>,
+    /// Beat of the recording the next tick is recorded at, none when not recording
+    recording_beat: Option<u64>,
 }
 
 pub enum Message {
     SpawnGrid,
-    DeleteGrid(usize),
+    DeleteGrid(GridId),
+    SelectPreset(String),
+    SelectEngine(Engine),
+    SelectSymmetry(Symmetry),
+    /// Plays the next beat of the transport, if it is the tick of the current clock
+    Tick(u64),
+    Edit(GridId, Edit),
+    Transport(Control),
+    SelectStrategy(Strategy),
+    /// Picks what happens to all the grids that stopped evolving
+    SelectPolicy(Policy),
+    /// Picks the scale from the catalogue, in the key of the current one
+    SelectScale(ScaleKind),
+    SelectKey(Note),
+    /// Parses the notes of a custom scale
+    SetScale(String),
+    SelectTuning(String),
+    SelectReferencePitch(f32),
+    /// Picks the velocity curve by its name
+    SelectVelocityCurve(String),
+    /// Reads the tuning from a Scala `.scl` file
+    LoadScala(File),
+    ScalaLoaded(FileData),
+    /// Reads a pattern file into the middle of the grid
+    LoadPattern(GridId, File),
+    PatternLoaded(GridId, FileData),
+    /// Starts a new recording or stops the current one
+    ToggleRecording,
+    /// Saves the recording as a MIDI file
+    DownloadRecording,
+    /// Saves the next generations of the grids as a WAV file
+    DownloadAudio,
 }
 
 impl App {
-    fn grid_view(&self, grid: &Grid, index: usize) -> Html {
-        let on_delete = self.link.callback(move |_| Message::DeleteGrid(index));
+    fn grid_view(&self, id: GridId, grid: &Grid) -> Html {
+        let on_delete = self.link.callback(move |_| Message::DeleteGrid(id));
+        let on_edit = self.link.callback(move |edit| Message::Edit(id, edit));
+        let on_load_pattern = self
+            .link
+            .callback(move |file| Message::LoadPattern(id, file));
+        let delta = self
+            .deltas
+            .iter()
+            .find(|(delta_id, _)| *delta_id == id)
+            .map(|(_, delta)| delta.clone());
+        let changed = self
+            .changed
+            .as_ref()
+            .map_or(true, |changed| changed.contains(&id));
+        let division = self.division(id);
+        let organisms = self
+            .trackers
+            .get(&id)
+            .map_or(0, |tracker| tracker.organisms().len());
         html! {
-            <GridView on_delete=on_delete grid=grid />
+            <GridView
+                key=id.to_string()
+                on_delete=on_delete
+                on_edit=on_edit
+                on_load_pattern=on_load_pattern
+                grid=grid
+                delta=delta
+                changed=changed
+                division=division
+                organisms=organisms
+            />
+        }
+    }
+
+    fn division(&self, id: GridId) -> u32 {
+        self.divisions.get(&id).copied().unwrap_or(1)
+    }
+
+    /// Finds the organisms of the current generation of the grid
+    fn track(&mut self, id: GridId) {
+        if let Some(grid) = self.state.simulation.get(id) {
+            self.trackers.entry(id).or_default().track(grid);
+        }
+    }
+
+    fn schedule_tick(&self) {
+        let wait = Delay::new(self.transport.beat_duration());
+        let clock = self.clock;
+        send_future(self.link.clone(), async move {
+            if let Err(err) = wait.await {
+                log::warn!("timer failed: {}", err);
+            }
+            Message::Tick(clock)
+        });
+    }
+
+    fn control_transport(&mut self, control: Control) {
+        let was_playing = self.transport.is_playing();
+        self.transport.apply(control);
+        if was_playing != self.transport.is_playing() {
+            // forget the tick scheduled before the pause, so the clock doesn't run twice
+            self.clock += 1;
+            if self.transport.is_playing() {
+                self.schedule_tick();
+            }
+        }
+    }
+
+    /// Plays the subgrids of the grids in the order of the selected strategy, spread over a single
+    /// beat. The sounds of each grid are panned towards the columns where most of its changes
+    /// happened. The notes are recorded if `record` is set and the recording is on.
+    fn play(&mut self, grids: &[(GridId, Option<&GenerationDelta>)], record: bool) {
+        if grids.is_empty() {
+            return;
+        }
+        let beat = self.transport.beat_duration().as_secs_f64();
+        let (schedule, pans) =
+            self.strategy
+                .schedule_grids(&mut self.state.simulation, grids, beat);
+
+        let (scale, tuning, curve) = (&self.scale, &self.tuning, self.velocity_curve);
+        let soundgen = self.soundgen.get_or_insert_with(|| {
+            let mut soundgen = SoundGenerator::new();
+            soundgen.set_scale(scale.clone());
+            soundgen.set_tuning(tuning.clone());
+            soundgen.set_velocity_curve(curve);
+            soundgen
+        });
+        if let Err(err) = soundgen.play_schedule(&schedule, |grid| pans[grid].1) {
+            log::warn!("can't play the sounds: {}", err);
+        }
+        if let (true, Some(recording), Some(start)) =
+            (record, self.recording.as_mut(), self.recording_beat)
+        {
+            soundgen.record_schedule(recording, &schedule, start, beat, |grid| {
+                format!("Grid {}", pans[grid].0)
+            });
+        }
+    }
+
+    fn set_scale(&mut self, scale: Scale, kind: Option<ScaleKind>) {
+        if let Some(soundgen) = self.soundgen.as_mut() {
+            soundgen.set_scale(scale.clone());
+        }
+        self.scale = scale;
+        self.scale_kind = kind;
+    }
+
+    fn set_tuning(&mut self, tuning: Tuning, name: String) {
+        if let Some(soundgen) = self.soundgen.as_mut() {
+            soundgen.set_tuning(tuning.clone());
+        }
+        self.tuning = tuning;
+        self.tuning_name = name;
+    }
+
+    fn set_velocity_curve(&mut self, curve: VelocityCurve) {
+        if let Some(soundgen) = self.soundgen.as_mut() {
+            soundgen.set_velocity_curve(curve);
+        }
+        self.velocity_curve = curve;
+    }
+
+    fn load_scala(&mut self, file: File) {
+        let callback = self.link.callback(Message::ScalaLoaded);
+        match ReaderService::new().read_file(file, callback) {
+            Ok(task) => self.reader = Some(task),
+            Err(err) => log::warn!("can't read the file: {}", err),
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        if self.recording_beat.take().is_none() {
+            self.recording = Some(Recording::new(self.transport.bpm()));
+            self.recording_beat = Some(0);
+        }
+    }
+
+    fn download_recording(&self) {
+        let recording = match &self.recording {
+            Some(recording) => recording,
+            None => return,
+        };
+        let mut file = Vec::new();
+        if let Err(err) = recording.write(&mut file) {
+            log::warn!("can't write the recording: {}", err);
+            return;
+        }
+        if let Err(err) = download("session.mid", "audio/midi", &file) {
+            log::warn!("can't download the recording: {:?}", err);
+        }
+    }
+
+    /// Renders the next generations of a copy of the grids, leaving the played ones untouched
+    fn download_audio(&self) {
+        let mut simulation = self.state.simulation.clone();
+        let mut soundgen = SoundGenerator::with_backend(Renderer::default());
+        soundgen.set_scale(self.scale.clone());
+        soundgen.set_tuning(self.tuning.clone());
+        soundgen.set_velocity_curve(self.velocity_curve);
+        let beat = self.transport.beat_duration().as_secs_f64();
+        let mut file = Vec::new();
+        let rendered = render_session(
+            &mut simulation,
+            RENDERED_GENERATIONS,
+            self.strategy,
+            beat,
+            &mut soundgen,
+        )
+        .and_then(|_| {
+            soundgen
+                .backend()
+                .write_wav(&mut file, SampleFormat::default())
+        });
+        if let Err(err) = rendered {
+            log::warn!("can't render the audio: {}", err);
+            return;
+        }
+        if let Err(err) = download("session.wav", "audio/wav", &file) {
+            log::warn!("can't download the audio: {:?}", err);
+        }
+    }
+
+    /// Moves the grids due on the next beat to their next generation
+    fn tick(&mut self) -> ShouldRender {
+        let step = match self.transport.tick() {
+            Some(step) => step,
+            None => return false,
+        };
+        let generations: HashMap<GridId, u64> = self
+            .state
+            .simulation
+            .iter()
+            .map(|(id, grid)| (id, grid.generation()))
+            .collect();
+        let divisions = &self.divisions;
+        self.deltas = self
+            .state
+            .simulation
+            .next_gen_where(|id| transport::is_due(step, divisions.get(&id).copied().unwrap_or(1)));
+        // the grids rewritten by the stability policy have no delta, but they changed too
+        self.changed = Some(
+            self.state
+                .simulation
+                .iter()
+                .filter(|(id, grid)| generations.get(id) != Some(&grid.generation()))
+                .map(|(id, _)| id)
+                .collect(),
+        );
+        let changed: Vec<GridId> = self.changed.iter().flatten().copied().collect();
+        for id in changed {
+            self.track(id);
+        }
+        let deltas = self.deltas.clone();
+        let grids: Vec<_> = deltas
+            .iter()
+            .map(|(id, delta)| (*id, Some(delta)))
+            .collect();
+        self.play(&grids, true);
+        if let Some(beat) = self.recording_beat.as_mut() {
+            *beat += 1;
+        }
+        true
+    }
+
+    fn edit(&mut self, id: GridId, edit: Edit) {
+        if let Edit::SetDivision(division) = edit {
+            self.divisions.insert(id, division.max(1));
+            return;
+        }
+        let grid = match self.state.simulation.get_mut(id) {
+            Some(grid) => grid,
+            None => return,
+        };
+        match edit {
+            Edit::ToggleCell(offset) => {
+                if let Err(err) = grid.change_cell(offset) {
+                    log::warn!("can't change the cell: {}", err);
+                }
+            }
+            Edit::ToggleSimulation => grid.toggle(),
+            Edit::FastForward => {
+                if let Err(err) = self.hashlife.advance(grid, FAST_FORWARD_LOG2) {
+                    log::warn!("can't fast-forward: {}", err);
+                }
+            }
+            Edit::Seek(generation) => {
+                if let Err(err) = grid.seek(generation) {
+                    log::warn!("can't seek: {}", err);
+                }
+                self.play(&[(id, None)], false);
+            }
+            Edit::Undo => {
+                grid.undo();
+            }
+            Edit::Redo => {
+                grid.redo();
+            }
+            Edit::ToggleUnbounded => {
+                let unbounded = !grid.is_unbounded();
+                if let Err(err) = grid.set_unbounded(unbounded) {
+                    log::warn!("can't make the grid unbounded: {}", err);
+                }
+            }
+            Edit::CenterView => grid.center_view(),
+            Edit::ExportPattern(format) => match format.write_grid(grid) {
+                Ok(file) => {
+                    let name = format!("pattern.{}", format.extension());
+                    if let Err(err) = download(&name, "text/plain", file.as_bytes()) {
+                        log::warn!("can't download the pattern: {:?}", err);
+                    }
+                }
+                Err(err) => log::warn!("can't export the pattern: {}", err),
+            },
+            Edit::SetDivision(_) => {}
+        }
+    }
+
+    fn load_pattern(&mut self, id: GridId, file: File) {
+        let callback = self
+            .link
+            .callback(move |file| Message::PatternLoaded(id, file));
+        match ReaderService::new().read_file(file, callback) {
+            Ok(task) => self.pattern_reader = Some(task),
+            Err(err) => log::warn!("can't read the file: {}", err),
+        }
+    }
+
+    fn pattern_loaded(&mut self, id: GridId, file: FileData) {
+        let grid = match self.state.simulation.get_mut(id) {
+            Some(grid) => grid,
+            None => return,
+        };
+        let loaded = PatternFormat::from_file_name(&file.name)
+            .parse(&String::from_utf8_lossy(&file.content))
+            .and_then(|pattern| grid.load_pattern(&pattern, centered(grid, &pattern)));
+        if let Err(err) = loaded {
+            log::warn!("can't load the pattern: {}", err);
+        }
+    }
+
+    /// Creates a grid with the selected preset, patterns are placed in the middle of the grid
+    fn spawn_grid(&self) -> Grid {
+        match self.preset.as_str() {
+            EMPTY_PRESET => Grid::empty(),
+            RANDOM_PRESET => Grid::random_with_symmetry(self.symmetry),
+            name => {
+                let mut grid = Grid::empty();
+                if let Some(info) = library::find(name) {
+                    let pattern = info.pattern();
+                    let _ = grid.load_pattern(&pattern, centered(&grid, &pattern));
+                }
+                grid
+            }
+        }
+    }
+
+    fn transport_bar(&self) -> Html {
+        let transport = &self.transport;
+        let control = |control: Control| self.link.callback(move |_| Message::Transport(control));
+        let set_bpm = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Value(value) => {
+                Message::Transport(Control::SetBpm(value.parse().unwrap_or_default()))
+            }
+            _ => Message::Transport(Control::SetBpm(transport::DEFAULT_BPM)),
+        });
+        let set_time_signature = self.link.callback(|e: ChangeData| {
+            let index = match e {
+                ChangeData::Select(select) => select.value().parse().unwrap_or_default(),
+                _ => 0,
+            };
+            Message::Transport(Control::SetTimeSignature(
+                TIME_SIGNATURES.get(index).copied().unwrap_or_default(),
+            ))
+        });
+        let select_strategy = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => {
+                Message::SelectStrategy(select.value().parse().unwrap_or_default())
+            }
+            _ => Message::SelectStrategy(Strategy::default()),
+        });
+        let select_policy = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => {
+                Message::SelectPolicy(select.value().parse().unwrap_or_default())
+            }
+            _ => Message::SelectPolicy(Policy::default()),
+        });
+        let policy = self.state.simulation.stability_policy();
+        let time_signature = transport.time_signature();
+        html! {
+            <div class="transport">
+                {if transport.is_playing() {
+                    html! {
+                        <button class="button transport__button" onclick=control(Control::Pause)>
+                            <i class="fas fa-pause"></i>
+                        </button>
+                    }
+                } else {
+                    html! {
+                        <button class="button transport__button" onclick=control(Control::Play)>
+                            <i class="fas fa-play"></i>
+                        </button>
+                    }
+                }}
+                <button class="button transport__button" onclick=control(Control::Stop)>
+                    <i class="fas fa-stop"></i>
+                </button>
+                <input
+                    class="transport__bpm"
+                    type="number"
+                    min=MIN_BPM.to_string()
+                    max=MAX_BPM.to_string()
+                    value=transport.bpm().to_string()
+                    onchange=set_bpm
+                />
+                <span class="transport__label">{"BPM"}</span>
+                <select class="transport__signature" onchange=set_time_signature>
+                    {TIME_SIGNATURES.iter().enumerate().map(|(index, signature)| html! {
+                        <option value=index.to_string() selected=*signature == time_signature>
+                            {signature.to_string()}
+                        </option>
+                    }).collect::<Html>()}
+                </select>
+                <select class="transport__strategy" onchange=select_strategy>
+                    {Strategy::iter().map(|strategy| html! {
+                        <option value=strategy.to_string() selected=strategy == self.strategy>
+                            {strategy.to_string()}
+                        </option>
+                    }).collect::<Html>()}
+                </select>
+                <select class="transport__policy" onchange=select_policy>
+                    {Policy::iter().map(|option| html! {
+                        <option value=option.to_string() selected=StabilityPolicy::all(option) == policy>
+                            {option.to_string()}
+                        </option>
+                    }).collect::<Html>()}
+                </select>
+                {self.recording_buttons()}
+                {self.scale_picker()}
+                {self.tuning_picker()}
+                {self.velocity_picker()}
+                <span class="transport__position">
+                    {format!("{}.{}", transport.bar() + 1, transport.beat() + 1)}
+                </span>
+            </div>
+        }
+    }
+
+    /// Buttons recording the played notes and rendering the grids into files
+    fn recording_buttons(&self) -> Html {
+        html! {
+            <>
+                <button
+                    class=if self.recording_beat.is_some() {
+                        "button transport__button transport__button--recording"
+                    } else {
+                        "button transport__button"
+                    }
+                    onclick=self.link.callback(|_| Message::ToggleRecording)
+                >
+                    <i class="fas fa-circle"></i>
+                </button>
+                <button
+                    class="button transport__button"
+                    disabled=self.recording.as_ref().map_or(true, Recording::is_empty)
+                    onclick=self.link.callback(|_| Message::DownloadRecording)
+                >
+                    <i class="fas fa-download"></i>
+                </button>
+                <button
+                    class="button transport__button"
+                    disabled=self.state.simulation.iter().next().is_none()
+                    onclick=self.link.callback(|_| Message::DownloadAudio)
+                >
+                    <i class="fas fa-file-audio"></i>
+                </button>
+            </>
+        }
+    }
+
+    fn scale_picker(&self) -> Html {
+        let select_scale = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => {
+                Message::SelectScale(select.value().parse().unwrap_or_default())
+            }
+            _ => Message::SelectScale(ScaleKind::default()),
+        });
+        let select_key = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => {
+                Message::SelectKey(select.value().parse().unwrap_or_default())
+            }
+            _ => Message::SelectKey(Note::default()),
+        });
+        let set_scale = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Value(value) => Message::SetScale(value),
+            _ => Message::SetScale(String::new()),
+        });
+        html! {
+            <>
+                <select class="transport__scale" onchange=select_scale>
+                    <option value="" disabled=true selected=self.scale_kind.is_none()>
+                        {"Custom"}
+                    </option>
+                    {ScaleKind::iter().map(|kind| html! {
+                        <option value=kind.to_string() selected=Some(kind) == self.scale_kind>
+                            {kind.to_string()}
+                        </option>
+                    }).collect::<Html>()}
+                </select>
+                <select class="transport__key" onchange=select_key>
+                    {Note::iter().map(|note| html! {
+                        <option value=note.to_string() selected=note == self.scale.root()>
+                            {note.to_string()}
+                        </option>
+                    }).collect::<Html>()}
+                </select>
+                <input
+                    class="transport__notes"
+                    type="text"
+                    placeholder="C D Eb G Ab"
+                    title="Notes like C D Eb G Ab, or a root and degrees of the tuning like C 0 4 7 10 14"
+                    value=self.scale.to_string()
+                    onchange=set_scale
+                />
+            </>
+        }
+    }
+
+    fn velocity_picker(&self) -> Html {
+        let select_curve = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => Message::SelectVelocityCurve(select.value()),
+            _ => Message::SelectVelocityCurve(VELOCITY_CURVES[0].0.to_string()),
+        });
+        html! {
+            <select class="transport__velocity" onchange=select_curve>
+                {VELOCITY_CURVES.iter().map(|&(name, curve)| html! {
+                    <option value=name selected=curve == self.velocity_curve>
+                        {name}
+                    </option>
+                }).collect::<Html>()}
+            </select>
+        }
+    }
+
+    fn tuning_picker(&self) -> Html {
+        let select_tuning = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => Message::SelectTuning(select.value()),
+            _ => Message::SelectTuning(TUNINGS[0].to_string()),
+        });
+        let select_reference = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Select(select) => {
+                Message::SelectReferencePitch(select.value().parse().unwrap_or(STANDARD_PITCH))
+            }
+            _ => Message::SelectReferencePitch(STANDARD_PITCH),
+        });
+        let load_scala = self.link.batch_callback(|e: ChangeData| match e {
+            ChangeData::Files(files) => files.get(0).map(Message::LoadScala).into_iter().collect(),
+            _ => Vec::new(),
+        });
+        let is_preset = TUNINGS.contains(&self.tuning_name.as_str());
+        html! {
+            <>
+                <select class="transport__tuning" onchange=select_tuning>
+                    {if is_preset {
+                        html! {}
+                    } else {
+                        html! {
+                            <option value="" disabled=true selected=true>
+                                {&self.tuning_name}
+                            </option>
+                        }
+                    }}
+                    {TUNINGS.iter().map(|&name| html! {
+                        <option value=name selected=name == self.tuning_name>{name}</option>
+                    }).collect::<Html>()}
+                </select>
+                <select class="transport__reference" onchange=select_reference>
+                    {REFERENCE_PITCHES.iter().map(|&pitch| html! {
+                        <option
+                            value=pitch.to_string()
+                            selected=pitch == self.tuning.reference()
+                        >
+                            {format!("A4 = {} Hz", pitch)}
+                        </option>
+                    }).collect::<Html>()}
+                </select>
+                <label class="button transport__button transport__scala">
+                    <i class="fas fa-file-import"></i>
+                    <input type="file" accept=".scl" onchange=load_scala />
+                </label>
+            </>
+        }
+    }
+
+    fn preset_picker(&self) -> Html {
+        let on_change = self.link.callback(|data| match data {
+            ChangeData::Select(select) => Message::SelectPreset(select.value()),
+            _ => Message::SelectPreset(EMPTY_PRESET.to_string()),
+        });
+        let presets = [EMPTY_PRESET, RANDOM_PRESET]
+            .iter()
+            .copied()
+            .chain(library::PATTERNS.iter().map(|info| info.name));
+        html! {
+            <select class="presets" onchange=on_change>
+                {presets.map(|name| html! {
+                    <option value=name selected=name == self.preset>{name}</option>
+                }).collect::<Html>()}
+            </select>
+        }
+    }
+
+    fn engine_picker(&self) -> Html {
+        let on_change = self.link.callback(|data| match data {
+            ChangeData::Select(select) => {
+                Message::SelectEngine(select.value().parse().unwrap_or_default())
+            }
+            _ => Message::SelectEngine(Engine::default()),
+        });
+        html! {
+            <select class="presets" onchange=on_change>
+                {Engine::iter().map(|engine| html! {
+                    <option value=engine.to_string() selected=engine == self.engine>
+                        {engine.to_string()}
+                    </option>
+                }).collect::<Html>()}
+            </select>
+        }
+    }
+
+    fn symmetry_picker(&self) -> Html {
+        if self.preset != RANDOM_PRESET {
+            return html! {};
+        }
+        let on_change = self.link.callback(|data| match data {
+            ChangeData::Select(select) => {
+                Message::SelectSymmetry(select.value().parse().unwrap_or_default())
+            }
+            _ => Message::SelectSymmetry(Symmetry::default()),
+        });
+        html! {
+            <select class="presets" onchange=on_change>
+                {Symmetry::iter().map(|symmetry| html! {
+                    <option value=symmetry.to_string() selected=symmetry == self.symmetry>
+                        {symmetry.to_string()}
+                    </option>
+                }).collect::<Html>()}
+            </select>
         }
     }
 }
@@ -40,10 +820,33 @@ impl Component for App {
     type Message = Message;
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self {
+        let app = Self {
             link,
             state: State::new(),
-        }
+            preset: EMPTY_PRESET.to_string(),
+            engine: Engine::Bitwise,
+            symmetry: Symmetry::default(),
+            hashlife: HashLife::default(),
+            deltas: Vec::new(),
+            changed: None,
+            transport: Transport::default(),
+            divisions: HashMap::new(),
+            trackers: HashMap::new(),
+            clock: 0,
+            strategy: Strategy::default(),
+            scale: ScaleKind::default().scale(Note::default()),
+            scale_kind: Some(ScaleKind::default()),
+            tuning: Tuning::default(),
+            tuning_name: TUNINGS[0].to_string(),
+            velocity_curve: VelocityCurve::default(),
+            reader: None,
+            pattern_reader: None,
+            soundgen: None,
+            recording: None,
+            recording_beat: None,
+        };
+        app.schedule_tick();
+        app
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
@@ -51,16 +854,135 @@ impl Component for App {
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        // the deltas are only valid right after the tick they come from
+        self.deltas.clear();
+        self.changed = None;
         match msg {
+            Message::Tick(clock) => {
+                if clock != self.clock {
+                    return false;
+                }
+                self.schedule_tick();
+                self.tick()
+            }
+            Message::Transport(control) => {
+                self.control_transport(control);
+                true
+            }
+            Message::SelectStrategy(strategy) => {
+                self.strategy = strategy;
+                false
+            }
+            Message::SelectPolicy(policy) => {
+                self.state
+                    .simulation
+                    .set_stability_policy(StabilityPolicy::all(policy));
+                false
+            }
+            Message::SelectScale(kind) => {
+                self.set_scale(kind.scale(self.scale.root()), Some(kind));
+                true
+            }
+            Message::SelectKey(root) => {
+                self.set_scale(self.scale.transpose(root), self.scale_kind);
+                true
+            }
+            Message::SetScale(notes) => {
+                match notes.parse() {
+                    Ok(scale) => self.set_scale(scale, None),
+                    Err(err) => log::warn!("can't use the scale: {}", err),
+                }
+                true
+            }
+            Message::SelectTuning(name) => {
+                let tuning = tuning_preset(&name).with_reference(self.tuning.reference());
+                self.set_tuning(tuning, name);
+                true
+            }
+            Message::SelectReferencePitch(reference) => {
+                let tuning = self.tuning.clone().with_reference(reference);
+                self.set_tuning(tuning, self.tuning_name.clone());
+                true
+            }
+            Message::SelectVelocityCurve(name) => {
+                if let Some(&(_, curve)) =
+                    VELOCITY_CURVES.iter().find(|(preset, _)| *preset == name)
+                {
+                    self.set_velocity_curve(curve);
+                }
+                true
+            }
+            Message::LoadScala(file) => {
+                self.load_scala(file);
+                false
+            }
+            Message::ScalaLoaded(file) => {
+                self.reader = None;
+                match Tuning::from_scala(&String::from_utf8_lossy(&file.content)) {
+                    Ok(tuning) => {
+                        let tuning = tuning.with_reference(self.tuning.reference());
+                        self.set_tuning(tuning, file.name);
+                    }
+                    Err(err) => log::warn!("can't use the tuning: {}", err),
+                }
+                true
+            }
+            Message::LoadPattern(id, file) => {
+                self.load_pattern(id, file);
+                false
+            }
+            Message::PatternLoaded(id, file) => {
+                self.pattern_reader = None;
+                self.pattern_loaded(id, file);
+                self.track(id);
+                true
+            }
+            Message::ToggleRecording => {
+                self.toggle_recording();
+                true
+            }
+            Message::DownloadRecording => {
+                self.download_recording();
+                false
+            }
+            Message::DownloadAudio => {
+                self.download_audio();
+                false
+            }
+            Message::Edit(id, edit) => {
+                self.edit(id, edit);
+                self.track(id);
+                true
+            }
             Message::SpawnGrid => {
                 // TODO: push new board when they are ready instead of a number
-                self.state.simulation.add_game(Grid::empty());
+                let mut grid = self.spawn_grid();
+                grid.set_engine(self.engine);
+                let id = self.state.simulation.add_game(grid);
+                self.track(id);
+                true
+            }
+            Message::DeleteGrid(id) => {
+                if let Err(err) = self.state.simulation.remove_game(id) {
+                    log::warn!("can't delete the grid: {}", err);
+                }
+                self.divisions.remove(&id);
+                self.trackers.remove(&id);
                 true
             }
-            Message::DeleteGrid(index) => {
-                self.state.simulation.remove_game(index);
+            Message::SelectPreset(preset) => {
+                // the symmetry picker is shown only for the random boards
+                self.preset = preset;
                 true
             }
+            Message::SelectEngine(engine) => {
+                self.engine = engine;
+                false
+            }
+            Message::SelectSymmetry(symmetry) => {
+                self.symmetry = symmetry;
+                false
+            }
 
             _ => false,
         }
@@ -82,11 +1004,17 @@ impl Component for App {
                     <a href="https://github.com/Sniadekk/rusty-days-hackathon"> {"Repository"} </a>
                 </div>
             </div>
+            {self.transport_bar()}
             <div class="grids">
-                {self.state.simulation.iter().enumerate().map(|(i, g)| self.grid_view(&g, i)).collect::<Html>()}
-                <button class="button add" onclick=spawn_grid>
-                    <i class="fas fa-plus"></i>
-                </button>
+                {self.state.simulation.iter().map(|(id, g)| self.grid_view(id, g)).collect::<Html>()}
+                <div class="spawn">
+                    {self.preset_picker()}
+                    {self.engine_picker()}
+                    {self.symmetry_picker()}
+                    <button class="button add" onclick=spawn_grid>
+                        <i class="fas fa-plus"></i>
+                    </button>
+                </div>
             </div>
             </div>
         }
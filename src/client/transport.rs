@@ -0,0 +1,232 @@
+//! Shared clock of all the boards. The transport counts the beats, the boards evolve on them.
+
+use core::time::Duration;
+
+pub const DEFAULT_BPM: u32 = 120;
+pub const MIN_BPM: u32 = 20;
+pub const MAX_BPM: u32 = 300;
+
+/// Clock divisions a board can be played at, in beats per generation
+pub const DIVISIONS: [u32; 4] = [1, 2, 4, 8];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSignature {
+    /// Number of beats in a bar
+    pub beats: u32,
+    /// Note value of a single beat
+    pub unit: u32,
+}
+
+impl TimeSignature {
+    pub const fn new(beats: u32, unit: u32) -> Self {
+        Self { beats, unit }
+    }
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self::new(4, 4)
+    }
+}
+
+impl std::fmt::Display for TimeSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.beats, self.unit)
+    }
+}
+
+/// Time signatures offered in the transport bar
+pub const TIME_SIGNATURES: [TimeSignature; 5] = [
+    TimeSignature::new(4, 4),
+    TimeSignature::new(3, 4),
+    TimeSignature::new(5, 4),
+    TimeSignature::new(6, 8),
+    TimeSignature::new(7, 8),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Playing,
+    Paused,
+    /// Paused, with the step counter back at the start
+    Stopped,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::Playing
+    }
+}
+
+/// Changes of the transport requested from the transport bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    Play,
+    Pause,
+    Stop,
+    SetBpm(u32),
+    SetTimeSignature(TimeSignature),
+}
+
+#[derive(Debug, Clone)]
+pub struct Transport {
+    bpm: u32,
+    time_signature: TimeSignature,
+    state: State,
+    /// Number of beats played since the last stop
+    step: u64,
+}
+
+impl Transport {
+    pub fn new(bpm: u32) -> Self {
+        Self {
+            bpm: bpm.max(MIN_BPM).min(MAX_BPM),
+            time_signature: TimeSignature::default(),
+            state: State::default(),
+            step: 0,
+        }
+    }
+
+    pub fn bpm(&self) -> u32 {
+        self.bpm
+    }
+
+    pub fn set_bpm(&mut self, bpm: u32) {
+        self.bpm = bpm.max(MIN_BPM).min(MAX_BPM);
+    }
+
+    pub fn time_signature(&self) -> TimeSignature {
+        self.time_signature
+    }
+
+    pub fn set_time_signature(&mut self, time_signature: TimeSignature) {
+        self.time_signature = time_signature;
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.state == State::Playing
+    }
+
+    pub fn play(&mut self) {
+        self.state = State::Playing;
+    }
+
+    pub fn pause(&mut self) {
+        if self.is_playing() {
+            self.state = State::Paused;
+        }
+    }
+
+    /// Pauses the transport and rewinds the step counter
+    pub fn stop(&mut self) {
+        self.state = State::Stopped;
+        self.step = 0;
+    }
+
+    pub fn apply(&mut self, control: Control) {
+        match control {
+            Control::Play => self.play(),
+            Control::Pause => self.pause(),
+            Control::Stop => self.stop(),
+            Control::SetBpm(bpm) => self.set_bpm(bpm),
+            Control::SetTimeSignature(time_signature) => self.set_time_signature(time_signature),
+        }
+    }
+
+    /// Time between two beats
+    pub fn beat_duration(&self) -> Duration {
+        Duration::from_secs_f64(60.0 / self.bpm as f64)
+    }
+
+    /// Number of the next beat to be played
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// Bar of the next beat, counted from 0
+    pub fn bar(&self) -> u64 {
+        self.step / self.time_signature.beats as u64
+    }
+
+    /// Position of the next beat in its bar, counted from 0
+    pub fn beat(&self) -> u64 {
+        self.step % self.time_signature.beats as u64
+    }
+
+    /// Plays the next beat and returns its step, or `None` if the transport isn't playing
+    pub fn tick(&mut self) -> Option<u64> {
+        if !self.is_playing() {
+            return None;
+        }
+        let step = self.step;
+        self.step += 1;
+        Some(step)
+    }
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::new(DEFAULT_BPM)
+    }
+}
+
+/// Whether a board playing one generation every `division` beats evolves on the `step`
+pub fn is_due(step: u64, division: u32) -> bool {
+    step % division.max(1) as u64 == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counting_beats() {
+        let mut transport = Transport::default();
+        transport.set_time_signature(TimeSignature::new(3, 4));
+        let steps: Vec<Option<u64>> = (0..4).map(|_| transport.tick()).collect();
+        assert_eq!(steps, vec![Some(0), Some(1), Some(2), Some(3)]);
+        assert_eq!((transport.bar(), transport.beat()), (1, 1));
+
+        transport.pause();
+        assert_eq!(transport.tick(), None);
+        transport.play();
+        assert_eq!(transport.tick(), Some(4));
+
+        transport.stop();
+        assert_eq!(transport.tick(), None);
+        assert_eq!(transport.step(), 0);
+        transport.play();
+        assert_eq!(transport.tick(), Some(0));
+    }
+
+    #[test]
+    fn stop_stays_stopped() {
+        let mut transport = Transport::default();
+        transport.stop();
+        transport.pause();
+        assert_eq!(transport.state(), State::Stopped);
+    }
+
+    #[test]
+    fn tempo() {
+        let mut transport = Transport::new(120);
+        assert_eq!(transport.beat_duration(), Duration::from_millis(500));
+
+        transport.set_bpm(1000);
+        assert_eq!(transport.bpm(), MAX_BPM);
+        transport.set_bpm(0);
+        assert_eq!(transport.bpm(), MIN_BPM);
+    }
+
+    #[test]
+    fn divisions() {
+        let due: Vec<u64> = (0..9).filter(|&step| is_due(step, 4)).collect();
+        assert_eq!(due, vec![0, 4, 8]);
+        assert!((0..9).all(|step| is_due(step, 1)));
+        assert!(is_due(3, 0));
+    }
+}
@@ -0,0 +1,366 @@
+//! Word-parallel engine computing the next generation of a grid. Every row of the grid is
+//! packed into the bits of `u64` words and the neighbors of 64 cells are counted at once with
+//! bitwise adders.
+//!
+//! The grid is surrounded with a one cell wide halo, which is filled according to the topology
+//! of the grid. Thanks to that the adders don't have to care about the edges at all.
+
+use super::grid::Grid;
+use super::index::Index;
+use super::rule::Rule;
+
+const WORD_BITS: usize = 64;
+
+/// Cells packed into bits, row by row. Bit `c % 64` of the word `c / 64` of a row is the cell
+/// in the column `c`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let words_per_row = (width + WORD_BITS - 1) / WORD_BITS;
+        Self {
+            width,
+            height,
+            words_per_row,
+            words: vec![0; words_per_row * height],
+        }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        let word = self.words[row * self.words_per_row + col / WORD_BITS];
+        word >> (col % WORD_BITS) & 1 == 1
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: bool) {
+        let word = &mut self.words[row * self.words_per_row + col / WORD_BITS];
+        let mask = 1 << (col % WORD_BITS);
+        if value {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    fn row(&self, row: usize) -> &[u64] {
+        &self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    /// Returns the alive cells of the grid one generation later
+    pub fn next_gen(grid: &Grid) -> Self {
+        let width = grid.width();
+        let height = grid.height();
+        let padded = Self::padded(grid);
+        let rule = grid.rule();
+        let (birth, survival) = Self::rule_masks(&rule);
+
+        let mut next = Self::new(width + 2, height + 2);
+        let words_per_row = padded.words_per_row;
+
+        for row in 1..=height {
+            let above = padded.row(row - 1);
+            let current = padded.row(row);
+            let below = padded.row(row + 1);
+
+            for w in 0..words_per_row {
+                // count the 8 neighbors into four bit planes
+                let mut planes = [0u64; 4];
+                for line in &[above, below] {
+                    add(&mut planes, west(line, w));
+                    add(&mut planes, line[w]);
+                    add(&mut planes, east(line, w));
+                }
+                add(&mut planes, west(current, w));
+                add(&mut planes, east(current, w));
+
+                let mut born = 0;
+                let mut survives = 0;
+                for count in 0..=8 {
+                    let equal = equal_to(&planes, count);
+                    if birth[count] {
+                        born |= equal;
+                    }
+                    if survival[count] {
+                        survives |= equal;
+                    }
+                }
+
+                let alive = current[w];
+                next.words[row * words_per_row + w] = (alive & survives) | (!alive & born);
+            }
+        }
+
+        let mut next = next.unpadded(width, height);
+        Self::fix_self_neighbors(grid, &mut next);
+        next
+    }
+
+    /// A cell is never its own neighbor, but the halo doesn't know that. When the topology glues
+    /// a cell on the edge onto itself, its next state is computed one by one instead.
+    fn fix_self_neighbors(grid: &Grid, next: &mut Self) {
+        let width = grid.width();
+        let height = grid.height();
+        let topology = grid.topology();
+        let rule = grid.rule();
+
+        let wraps_onto_itself = |row: usize, col: usize| {
+            (-1..=1).any(|x: isize| {
+                (-1..=1).any(|y: isize| {
+                    let (r, c) = (row as isize + x, col as isize + y);
+                    let outside = r < 0 || r >= height as isize || c < 0 || c >= width as isize;
+                    outside && topology.wrap(r, c, width, height) == Some(Index { row, col })
+                })
+            })
+        };
+
+        let edges = (0..width)
+            .map(|col| (0, col))
+            .chain((0..width).map(|col| (height - 1, col)))
+            .chain((0..height).map(|row| (row, 0)))
+            .chain((0..height).map(|row| (row, width - 1)));
+
+        for (row, col) in edges {
+            if wraps_onto_itself(row, col) {
+                let index = Index { row, col };
+                let alive = grid
+                    .get_cell(index.to_offset(width))
+                    .map_or(false, |cell| cell.alive);
+                next.set(
+                    row,
+                    col,
+                    rule.next_state(alive, grid.count_neighbors(index)),
+                );
+            }
+        }
+    }
+
+    /// Copies the grid into a bit grid surrounded with a halo of cells glued to the edges
+    fn padded(grid: &Grid) -> Self {
+        let width = grid.width();
+        let height = grid.height();
+        let topology = grid.topology();
+        let mut padded = Self::new(width + 2, height + 2);
+
+        for (idx, cell) in grid.iter().enumerate() {
+            if cell.alive {
+                padded.set(idx / width + 1, idx % width + 1, true);
+            }
+        }
+
+        let mut fill_halo = |row: isize, col: isize| {
+            let alive = topology
+                .wrap(row, col, width, height)
+                .and_then(|index| grid.get_cell(index.to_offset(width)))
+                .map_or(false, |cell| cell.alive);
+            padded.set((row + 1) as usize, (col + 1) as usize, alive);
+        };
+
+        for col in -1..=width as isize {
+            fill_halo(-1, col);
+            fill_halo(height as isize, col);
+        }
+        for row in 0..height as isize {
+            fill_halo(row, -1);
+            fill_halo(row, width as isize);
+        }
+
+        padded
+    }
+
+    /// Strips the halo off a padded bit grid
+    fn unpadded(&self, width: usize, height: usize) -> Self {
+        let mut bits = Self::new(width, height);
+        for row in 0..height {
+            let padded = self.row(row + 1);
+            for w in 0..bits.words_per_row {
+                // east neighbor of the column `c` in the padded grid is the column `c` here
+                bits.words[row * bits.words_per_row + w] = east(padded, w);
+            }
+            if width % WORD_BITS != 0 {
+                bits.words[(row + 1) * bits.words_per_row - 1] &= (1 << (width % WORD_BITS)) - 1;
+            }
+        }
+        bits
+    }
+
+    fn rule_masks(rule: &Rule) -> ([bool; 9], [bool; 9]) {
+        let mut birth = [false; 9];
+        let mut survival = [false; 9];
+        for count in 0..=8 {
+            birth[count] = rule.is_born(count);
+            survival[count] = rule.survives(count);
+        }
+        (birth, survival)
+    }
+}
+
+/// Adds a single bit to every lane of the bit planes with a ripple-carry adder
+fn add(planes: &mut [u64; 4], bits: u64) {
+    let mut carry = bits;
+    for plane in planes.iter_mut() {
+        let next_carry = *plane & carry;
+        *plane ^= carry;
+        carry = next_carry;
+    }
+}
+
+/// Returns the lanes in which the bit planes hold the `count`
+fn equal_to(planes: &[u64; 4], count: usize) -> u64 {
+    planes.iter().enumerate().fold(!0, |acc, (bit, &plane)| {
+        if count >> bit & 1 == 1 {
+            acc & plane
+        } else {
+            acc & !plane
+        }
+    })
+}
+
+/// Moves the cells one column to the right, so every cell sees its western neighbor
+fn west(line: &[u64], w: usize) -> u64 {
+    let carry = if w > 0 {
+        line[w - 1] >> (WORD_BITS - 1)
+    } else {
+        0
+    };
+    line[w] << 1 | carry
+}
+
+/// Moves the cells one column to the left, so every cell sees its eastern neighbor
+fn east(line: &[u64], w: usize) -> u64 {
+    let carry = if w + 1 < line.len() {
+        line[w + 1] << (WORD_BITS - 1)
+    } else {
+        0
+    };
+    line[w] >> 1 | carry
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conway::{Engine, Rule, Topology};
+    use std::time::Instant;
+
+    const TOPOLOGIES: [Topology; 6] = [
+        Topology::Bounded,
+        Topology::Torus,
+        Topology::KleinBottle,
+        Topology::CrossSurface,
+        Topology::HorizontalCylinder,
+        Topology::VerticalCylinder,
+    ];
+
+    fn assert_engines_agree(width: usize, height: usize, topology: Topology, rule: &str) {
+        let mut cellwise = Grid::random_with_size(width, height);
+        cellwise.set_topology(topology);
+        cellwise.set_rule(rule.parse().unwrap());
+        cellwise.start();
+
+        let mut bitwise = cellwise.clone();
+        bitwise.set_engine(Engine::Bitwise);
+
+        for generation in 0..20 {
+            cellwise.next_gen();
+            bitwise.next_gen();
+            assert!(
+                cellwise
+                    .iter()
+                    .zip(bitwise.iter())
+                    .all(|(c, b)| c.alive == b.alive
+                        && c.just_changed == b.just_changed
+                        && c.age == b.age
+                        && c.decay == b.decay),
+                "engines differ in generation {} of a {}x{} {:?} grid with {}",
+                generation,
+                width,
+                height,
+                topology,
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn engines_agree_on_topologies() {
+        for &topology in TOPOLOGIES.iter() {
+            assert_engines_agree(50, 50, topology, "B3/S23");
+            assert_engines_agree(130, 37, topology, "B3/S23");
+        }
+    }
+
+    #[test]
+    fn engines_agree_on_word_boundaries() {
+        for &width in &[1, 2, 3, 62, 63, 64, 65, 127, 128, 129] {
+            assert_engines_agree(width, 9, Topology::Bounded, "B3/S23");
+            assert_engines_agree(width, 9, Topology::Torus, "B3/S23");
+        }
+    }
+
+    #[test]
+    fn engines_agree_on_rules() {
+        for rule in &["B36/S23", "B2/S", "B3678/S34678", "B0/S8", "B/S012345678"] {
+            assert_engines_agree(70, 40, Topology::Torus, rule);
+            assert_engines_agree(70, 40, Topology::Bounded, rule);
+        }
+    }
+
+    #[test]
+    fn dying_states_fall_back_to_cellwise() {
+        let mut grid = Grid::random_with_size(40, 40);
+        grid.set_rule(Rule::brians_brain());
+        grid.set_engine(Engine::Bitwise);
+        grid.start();
+        grid.next_gen();
+
+        // the bitwise engine never leaves dying cells behind
+        assert!(grid.iter().any(|cell| cell.decay > 0));
+        assert_eq!(grid.engine(), Engine::Bitwise);
+    }
+
+    #[test]
+    fn packing() {
+        let mut bits = BitGrid::new(100, 3);
+        bits.set(2, 99, true);
+        bits.set(1, 64, true);
+        bits.set(0, 63, true);
+        assert!(bits.get(2, 99) && bits.get(1, 64) && bits.get(0, 63));
+        assert!(!bits.get(2, 98) && !bits.get(1, 63) && !bits.get(0, 64));
+
+        bits.set(1, 64, false);
+        assert!(!bits.get(1, 64));
+    }
+
+    /// Compares the speed of both engines, run with `cargo test -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_engines() {
+        for &(width, height) in &[(50, 50), (200, 120)] {
+            let mut cellwise = Grid::random_with_size(width, height);
+            cellwise.start();
+            let mut bitwise = cellwise.clone();
+            bitwise.set_engine(Engine::Bitwise);
+
+            let now = Instant::now();
+            for _ in 0..100 {
+                cellwise.next_gen();
+            }
+            let cellwise_time = now.elapsed().as_secs_f32();
+
+            let now = Instant::now();
+            for _ in 0..100 {
+                bitwise.next_gen();
+            }
+            let bitwise_time = now.elapsed().as_secs_f32();
+
+            println!(
+                "{}x{}, 100 generations: cellwise {}s, bitwise {}s",
+                width, height, cellwise_time, bitwise_time
+            );
+        }
+    }
+}
@@ -1,18 +1,79 @@
+use std::borrow::Cow;
+
 /// size of a single cell
 pub const CELL_SIZE: usize = 10;
 
-#[derive(Debug, Clone, Copy)]
+/// Age at which the color of an alive cell stops changing
+const AGE_GRADIENT: u32 = 30;
+
+/// Colors of the dying cells, the ones dying for longer have the last color
+const DECAY_COLORS: [&str; 5] = [
+    "hsl(280, 70%, 50%)",
+    "hsl(280, 70%, 40%)",
+    "hsl(280, 70%, 30%)",
+    "hsl(280, 70%, 20%)",
+    "hsl(280, 70%, 10%)",
+];
+
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Cell {
     pub alive: bool,
     pub just_changed: bool,
+    /// Number of generations the cell has been alive or dead for
+    pub age: u32,
+    /// Number of generations the cell has been dying for. Only the Generations rules have dying
+    /// cells, these are not alive, don't count as neighbors and can't be born.
+    pub decay: u8,
 }
 
 impl Cell {
-    pub fn color(&self) -> &str {
+    /// Moves the cell to the next generation, in which it is `alive` or not. `states` is the
+    /// number of states of the rule, the cells of the rules with more than two states decay after
+    /// their death.
+    pub fn next(self, alive: bool, states: u8) -> Cell {
+        let decay = if alive {
+            0
+        } else if self.alive {
+            (states > 2) as u8
+        } else if self.decay > 0 && self.decay + 2 < states {
+            self.decay + 1
+        } else {
+            0
+        };
+
+        Cell {
+            alive,
+            just_changed: alive != self.alive,
+            age: if alive == self.alive {
+                self.age.saturating_add(1)
+            } else {
+                0
+            },
+            decay,
+        }
+    }
+
+    /// Whether the color of the cell changed in its last generation. The colors of the dying
+    /// cells are not covered, these change with their dying state.
+    pub fn is_recolored(&self) -> bool {
+        self.alive && self.age <= AGE_GRADIENT
+    }
+
+    /// Returns the color of the cell. Alive cells go from white to blue as they get older and
+    /// dying cells fade out. Only the colors of the alive cells are allocated.
+    pub fn color(&self) -> Cow<'static, str> {
         if self.alive {
-            "#fff"
+            let t = self.age.min(AGE_GRADIENT) as f32 / AGE_GRADIENT as f32;
+            Cow::Owned(format!(
+                "hsl({:.0}, 100%, {:.0}%)",
+                50.0 + 150.0 * t,
+                100.0 - 40.0 * t
+            ))
+        } else if self.decay > 0 {
+            let index = (self.decay as usize - 1).min(DECAY_COLORS.len() - 1);
+            Cow::Borrowed(DECAY_COLORS[index])
         } else {
-            "#000"
+            Cow::Borrowed("#000")
         }
     }
 }
@@ -21,7 +82,7 @@ impl Into<Cell> for bool {
     fn into(self) -> Cell {
         Cell {
             alive: self,
-            just_changed: false,
+            ..Default::default()
         }
     }
 }
@@ -0,0 +1,44 @@
+//! Cells that were born and died in a single generation, for the consumers that care about the
+//! positions of the changes rather than just their number.
+
+use super::index::Index;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenerationDelta {
+    /// Cells that became alive, in the order of their offsets
+    pub born: Vec<Index>,
+    /// Cells that died, in the order of their offsets
+    pub died: Vec<Index>,
+    /// Cells whose dying state changed, in the order of their offsets. Only the Generations rules
+    /// have dying cells.
+    pub decayed: Vec<Index>,
+}
+
+impl GenerationDelta {
+    /// Number of the cells that changed
+    pub fn len(&self) -> usize {
+        self.born.len() + self.died.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns iterator over all the changed cells, the births first
+    pub fn iter(&self) -> impl Iterator<Item = &Index> {
+        self.born.iter().chain(self.died.iter())
+    }
+
+    /// Average row and column of the changed cells, or `None` if nothing changed
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let (rows, cols) = self.iter().fold((0, 0), |(rows, cols), index| {
+            (rows + index.row, cols + index.col)
+        });
+        let len = self.len() as f64;
+        Some((rows as f64 / len, cols as f64 / len))
+    }
+}
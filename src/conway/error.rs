@@ -4,6 +4,24 @@ use thiserror::Error;
 pub enum GameError {
     #[error("index out of bounds")]
     IndexOutOfBounds(crate::conway::Index),
-    #[error("game index out of bounds")]
-    GameIndexOutOfBounds(usize),
+    #[error("unknown grid: {0}")]
+    UnknownGrid(crate::conway::GridId),
+    #[error("invalid rule: {0}")]
+    InvalidRule(String),
+    #[error("rule not supported here: {0}")]
+    UnsupportedRule(String),
+    #[error("topology not supported here: {0:?}")]
+    UnsupportedTopology(crate::conway::Topology),
+    #[error("not supported by unbounded grids")]
+    Unbounded,
+    #[error("can't advance by 2^{0} generations")]
+    TooManyGenerations(u8),
+    #[error("pattern of {width}x{height} cells doesn't fit into the grid")]
+    PatternTooLarge { width: usize, height: usize },
+    #[error("invalid pattern at line {line}: {message}")]
+    InvalidPattern { line: usize, message: String },
+    #[error("unknown pattern: {0}")]
+    UnknownPattern(String),
+    #[error("generation {0} is not in the history")]
+    GenerationNotInHistory(u64),
 }
@@ -1,116 +1,615 @@
 use rand::prelude::Rng;
 use rand::rngs::OsRng;
+use rand::seq::index;
 
+use super::bitboard::BitGrid;
 use super::cell::Cell;
+use super::delta::GenerationDelta;
 use super::index::Index;
+use super::pattern::{library, Pattern};
+use super::rule::Rule;
+use super::soup::{Soup, Symmetry, DEFAULT_DENSITY};
+use super::stability::{History, Policy, Stability, StabilityPolicy};
+use super::timeline::{Change, Frame, FrameKind, Timeline};
+use super::topology::Topology;
+use super::universe::{Position, Universe};
 use super::GameError;
 use super::Result;
+use std::convert::TryFrom;
+use strum_macros::{Display, EnumIter, EnumString};
 
-/// width of a single grid
+/// default width of a single grid
 pub const GRID_WIDTH: usize = 50;
-/// height of a single grid
+/// default height of a single grid
 pub const GRID_HEIGHT: usize = 50;
 
 pub const NUMBER_OF_SUBGRIDS: usize = 1;
 
+/// Fraction of the cells flipped when noise is injected into a grid
+pub const NOISE_DENSITY: f64 = 0.02;
+
 /// Iterator over the values of pitch and volume for each subgrid in the Grid
 pub type SubgridValuesIter<'g> = std::slice::Iter<'g, (u32, u32)>;
 
+/// The way the next generation is computed. Both engines produce identical generations.
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, EnumString, Display)]
+pub enum Engine {
+    /// Counts the neighbors of each cell one by one
+    Cellwise,
+    /// Packs the rows into bits and counts the neighbors of 64 cells at once. Rules with dying
+    /// states fall back to the cellwise engine.
+    Bitwise,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::Cellwise
+    }
+}
+
 #[derive(Clone)]
 pub struct Grid {
     sound: u32,
     pub stopped: bool,
-    cells: [Cell; GRID_WIDTH * GRID_HEIGHT],
+    width: usize,
+    height: usize,
+    rule: Rule,
+    topology: Topology,
+    engine: Engine,
+    /// Soup the grid was filled with, if it was created randomly
+    soup: Option<Soup>,
+    history: History,
+    /// Number of generations computed since the grid was created
+    generation: u64,
+    timeline: Timeline,
+    /// Unbounded universe the grid is a window onto, with the position of its top left corner
+    universe: Option<(Universe, Position)>,
+    cells: Vec<Cell>,
     subgrids: [(Index, Index); NUMBER_OF_SUBGRIDS],
     subgrid_values: [(u32, u32); NUMBER_OF_SUBGRIDS],
 }
 
 impl Grid {
-    pub fn new(cells: [Cell; GRID_WIDTH * GRID_HEIGHT], sound: u32, stopped: bool) -> Self {
+    /// Creates a grid of `width` x `height` cells. The number of `cells` has to match the
+    /// dimensions
+    pub fn new(cells: Vec<Cell>, width: usize, height: usize, sound: u32, stopped: bool) -> Self {
+        assert_eq!(
+            cells.len(),
+            width * height,
+            "number of cells doesn't match the dimensions of the grid"
+        );
+
         Self {
             cells,
             sound,
             stopped,
-            subgrids: Self::subgrids(),
+            width,
+            height,
+            rule: Rule::default(),
+            topology: Topology::default(),
+            engine: Engine::default(),
+            soup: None,
+            history: History::default(),
+            generation: 0,
+            timeline: Timeline::default(),
+            universe: None,
+            subgrids: Self::subgrids(width, height),
             subgrid_values: Default::default(),
         }
     }
 
     pub fn empty() -> Self {
-        let mut cells: [Cell; GRID_WIDTH * GRID_HEIGHT] = [false.into(); GRID_WIDTH * GRID_HEIGHT];
-        Self {
-            cells,
-            stopped: true,
-            sound: Default::default(),
-            subgrids: Self::subgrids(),
-            subgrid_values: Default::default(),
-        }
+        Self::empty_with_size(GRID_WIDTH, GRID_HEIGHT)
     }
 
+    pub fn empty_with_size(width: usize, height: usize) -> Self {
+        Self::new(
+            vec![false.into(); width * height],
+            width,
+            height,
+            Default::default(),
+            true,
+        )
+    }
+
+    /// Creates an empty grid of the default size with the pattern from the library stamped onto
+    /// it, with its top left corner at `offset`
+    pub fn with_pattern(name: &str, offset: Index) -> Result<Self> {
+        let info =
+            library::find(name).ok_or_else(|| GameError::UnknownPattern(name.to_string()))?;
+        let mut grid = Self::empty();
+        grid.load_pattern(&info.pattern(), offset)?;
+        Ok(grid)
+    }
+
+    /// Creates a grid of the default size filled with a soup from a random seed
     pub fn random() -> Grid {
-        let mut cells: [Cell; GRID_WIDTH * GRID_HEIGHT] = [false.into(); GRID_WIDTH * GRID_HEIGHT];
+        Self::random_with_size(GRID_WIDTH, GRID_HEIGHT)
+    }
 
-        cells.iter_mut().for_each(|cell| {
-            let value = OsRng.gen_bool(1.2 / 3.0).into();
-            // println!("{:?}", value);
-            *cell = value;
-        });
+    pub fn random_with_size(width: usize, height: usize) -> Grid {
+        Self::from_soup(Soup::new(OsRng.gen(), DEFAULT_DENSITY), width, height)
+    }
 
-        Self {
-            cells,
-            stopped: true,
-            sound: Default::default(),
-            subgrids: Self::subgrids(),
-            subgrid_values: Default::default(),
+    /// Creates a grid of the default size filled with a soup with the `symmetry` from a random
+    /// seed
+    pub fn random_with_symmetry(symmetry: Symmetry) -> Grid {
+        let soup = Soup::new(OsRng.gen(), DEFAULT_DENSITY).with_symmetry(symmetry);
+        Self::from_soup(soup, GRID_WIDTH, GRID_HEIGHT)
+    }
+
+    /// Creates a grid of the default size filled with a soup, which is always the same for the
+    /// same `seed` and `density`
+    pub fn random_with(seed: u64, density: f64) -> Grid {
+        Self::from_soup(Soup::new(seed, density), GRID_WIDTH, GRID_HEIGHT)
+    }
+
+    pub fn from_soup(soup: Soup, width: usize, height: usize) -> Grid {
+        let cells = soup
+            .generate(width, height)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        let mut grid = Self::new(cells, width, height, Default::default(), true);
+        grid.soup = Some(soup);
+        grid
+    }
+
+    /// Returns the soup the grid was filled with, it can be used to recreate the grid
+    pub fn soup(&self) -> Option<Soup> {
+        self.soup
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Sets the rule used to compute the next generations. Unbounded grids become bounded if the
+    /// universe doesn't support the rule.
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        if let Some((universe, _)) = self.universe.as_mut() {
+            if universe.set_rule(rule).is_err() {
+                self.universe = None;
+            }
         }
     }
 
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Sets how the edges of the grid are glued together
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    pub fn engine(&self) -> Engine {
+        self.engine
+    }
+
+    /// Sets the engine used to compute the next generations. Rules with dying states are always
+    /// computed cellwise.
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
+
     pub fn next_gen(&mut self) -> bool {
         if self.stopped {
             return false;
         }
 
-        let mut new_generation: [Cell; GRID_WIDTH * GRID_HEIGHT] =
-            [true.into(); GRID_HEIGHT * GRID_HEIGHT];
-        new_generation
-            .iter_mut()
+        self.advance();
+
+        true
+    }
+
+    /// Computes the next generation like `next_gen` and returns the cells that were born and died
+    /// in it. Returns `None` if the grid is stopped.
+    pub fn next_gen_with_delta(&mut self) -> Option<GenerationDelta> {
+        if self.stopped {
+            return None;
+        }
+
+        Some(self.advance())
+    }
+
+    /// Computes the next generation, even if the grid is stopped
+    fn advance(&mut self) -> GenerationDelta {
+        if self.history.is_empty() {
+            self.history.push(&self.cells);
+        }
+
+        // the bitwise engine doesn't know about the dying states
+        let engine = if self.rule.states() > 2 {
+            Engine::Cellwise
+        } else {
+            self.engine
+        };
+
+        let new_generation: Vec<Cell> = match engine {
+            _ if self.universe.is_some() => self.next_in_universe(),
+            Engine::Cellwise => (0..self.cells.len())
+                .map(|idx| {
+                    let neighbors = self.count_neighbors(Index::from_offset(idx, self.width));
+                    self.rule.next_cell(self.cells[idx], neighbors)
+                })
+                .collect(),
+            Engine::Bitwise => {
+                let next = BitGrid::next_gen(self);
+                self.cells
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, cell)| cell.next(next.get(idx / self.width, idx % self.width), 2))
+                    .collect()
+            }
+        };
+
+        let changes: Vec<Change> = self
+            .cells
+            .iter()
+            .zip(new_generation.iter())
             .enumerate()
-            .for_each(|(idx, cell)| {
-                let cell_alive = self.cells[idx].alive;
-                let neighbors = self.count_neighbors(idx);
-                *cell = match neighbors {
-                    2 if cell_alive => Cell {
-                        alive: true,
-                        just_changed: false,
-                    },
-                    3 => Cell {
-                        alive: true,
-                        just_changed: !cell_alive,
-                    },
-                    _ => Cell {
-                        alive: false,
-                        just_changed: cell_alive,
-                    },
-                };
-            });
+            .filter(|(_, (before, after))| {
+                before.alive != after.alive || before.decay != after.decay
+            })
+            .map(|(offset, (&before, &after))| Change {
+                offset,
+                before,
+                after,
+            })
+            .collect();
+
+        let mut delta = GenerationDelta::default();
+        for change in &changes {
+            let index = Index::from_offset(change.offset, self.width);
+            if change.just_changed() {
+                if change.after.alive {
+                    delta.born.push(index);
+                } else {
+                    delta.died.push(index);
+                }
+            }
+            if change.before.decay != change.after.decay {
+                delta.decayed.push(index);
+            }
+        }
+
+        self.timeline.push(Frame {
+            kind: FrameKind::Generation,
+            generations: 1,
+            changes,
+        });
 
         self.cells = new_generation;
+        self.history.push(&self.cells);
+        self.generation += 1;
+
+        delta
+    }
+
+    /// Moves the grid `generations` generations forward at once, to the `alive` cells computed by
+    /// another engine. The leap is a single frame of the timeline.
+    pub fn leap(&mut self, alive: impl IntoIterator<Item = bool>, generations: u64) {
+        let age = u32::try_from(generations).unwrap_or(u32::MAX);
+        let mut changes = Vec::new();
+        for (offset, (cell, alive)) in self.cells.iter_mut().zip(alive).enumerate() {
+            let before = *cell;
+            *cell = if alive == before.alive {
+                Cell {
+                    just_changed: false,
+                    age: before.age.saturating_add(age),
+                    ..before
+                }
+            } else {
+                Cell {
+                    alive,
+                    just_changed: true,
+                    ..Default::default()
+                }
+            };
+            if before.alive != alive {
+                changes.push(Change {
+                    offset,
+                    before,
+                    after: *cell,
+                });
+            }
+        }
 
+        self.sync_universe(changes.iter().map(|change| change.offset));
+        self.timeline.push(Frame {
+            kind: FrameKind::Generation,
+            generations,
+            changes,
+        });
+        self.generation += generations;
+        self.history.clear();
+    }
+
+    /// Computes the next generation of the universe of an unbounded grid and returns the cells
+    /// of its window
+    fn next_in_universe(&mut self) -> Vec<Cell> {
+        let (universe, origin) = match self.universe.as_mut() {
+            Some(universe) => universe,
+            None => return self.cells.clone(),
+        };
+        universe.next_gen();
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(idx, cell)| {
+                let position = origin.offset(Index::from_offset(idx, width));
+                cell.next(universe.is_alive(position), 2)
+            })
+            .collect()
+    }
+
+    /// Number of generations computed since the grid was created, minus the generations that
+    /// were stepped back
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Oldest generation the grid can step back to
+    pub fn oldest_generation(&self) -> u64 {
+        self.generation - self.timeline.past_generations()
+    }
+
+    /// Newest generation the grid can step forward to without computing it again
+    pub fn newest_generation(&self) -> u64 {
+        self.generation + self.timeline.future_generations()
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.universe.is_some()
+    }
+
+    /// Makes the grid a window onto an unbounded universe, so the patterns leaving the grid keep
+    /// evolving outside of it. Going back in time only restores the cells inside the window.
+    pub fn set_unbounded(&mut self, unbounded: bool) -> Result<()> {
+        if !unbounded {
+            self.universe = None;
+        } else if self.universe.is_none() {
+            let origin = Position::new(0, 0);
+            self.universe = Some((Universe::from_grid(self, origin)?, origin));
+        }
+        Ok(())
+    }
+
+    /// Moves the window of an unbounded grid to the middle of the alive cells of its universe.
+    /// The cells of the window are replaced in a single edit.
+    pub fn center_view(&mut self) {
+        let (universe, origin) = match self.universe.take() {
+            Some(universe) => universe,
+            None => return,
+        };
+        let origin = match universe.bounding_box() {
+            Some((min, max)) => Position::new(
+                min.row + (max.row - min.row) / 2 - self.height as i64 / 2,
+                min.col + (max.col - min.col) / 2 - self.width as i64 / 2,
+            ),
+            None => origin,
+        };
+        universe.render_into(self, origin);
+        self.universe = Some((universe, origin));
+    }
+
+    /// Goes back to the previous generation, reverting the edits made since then. Returns
+    /// `false` if the previous generation is no longer in the history.
+    pub fn step_back(&mut self) -> bool {
+        if self.timeline.past_generations() == 0 {
+            return false;
+        }
+
+        while let Some(frame) = self.timeline.undo() {
+            self.revert(&frame);
+            if frame.kind == FrameKind::Generation {
+                break;
+            }
+        }
+
+        true
+    }
+
+    /// Goes forward to the generation that was stepped back from, together with the edits made
+    /// in it. Returns `false` if there is no such generation.
+    pub fn step_forward(&mut self) -> bool {
+        if self.timeline.future_generations() == 0 {
+            return false;
+        }
+
+        while let Some(frame) = self.timeline.redo() {
+            self.apply(&frame);
+            if frame.kind == FrameKind::Generation {
+                break;
+            }
+        }
+
+        true
+    }
+
+    /// Moves the grid to the `generation`. Generations newer than any computed so far are
+    /// computed, even if the grid is stopped.
+    pub fn seek(&mut self, generation: u64) -> Result<()> {
+        if generation < self.oldest_generation() {
+            return Err(GameError::GenerationNotInHistory(generation));
+        }
+
+        while self.generation > generation {
+            self.step_back();
+        }
+        // the fast-forwarded generations are computed again if the target is in the middle of them
+        while self.generation < generation {
+            let fits = self
+                .timeline
+                .next_undone_generations()
+                .map_or(false, |generations| {
+                    self.generation + generations <= generation
+                });
+            if !fits || !self.step_forward() {
+                self.advance();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reverts the last edit made since the last generation. Returns `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        if self.timeline.last().map(|frame| frame.kind) != Some(FrameKind::Edit) {
+            return false;
+        }
+        let frame = self.timeline.undo().unwrap();
+        self.revert(&frame);
+        true
+    }
+
+    /// Makes the last undone edit again. Returns `false` if there is none.
+    pub fn redo(&mut self) -> bool {
+        if self.timeline.next_undone().map(|frame| frame.kind) != Some(FrameKind::Edit) {
+            return false;
+        }
+        let frame = self.timeline.redo().unwrap();
+        self.apply(&frame);
         true
     }
 
+    fn revert(&mut self, frame: &Frame) {
+        if frame.kind == FrameKind::Generation {
+            let age = u32::try_from(frame.generations).unwrap_or(u32::MAX);
+            for cell in self.cells.iter_mut() {
+                cell.age = cell.age.saturating_sub(age);
+                cell.just_changed = false;
+            }
+            self.generation -= frame.generations;
+        }
+
+        for change in frame.changes.iter().rev() {
+            self.cells[change.offset] = change.before;
+        }
+        self.sync_universe(frame.changes.iter().map(|change| change.offset));
+
+        // the cells that changed in the generation we went back to
+        if frame.kind == FrameKind::Generation {
+            if let Some(previous) = self
+                .timeline
+                .last()
+                .filter(|previous| previous.kind == FrameKind::Generation)
+            {
+                for change in &previous.changes {
+                    self.cells[change.offset].just_changed = change.after.just_changed;
+                }
+            }
+        }
+        self.history.clear();
+    }
+
+    fn apply(&mut self, frame: &Frame) {
+        if frame.kind == FrameKind::Generation {
+            let age = u32::try_from(frame.generations).unwrap_or(u32::MAX);
+            for cell in self.cells.iter_mut() {
+                cell.age = cell.age.saturating_add(age);
+                cell.just_changed = false;
+            }
+            self.generation += frame.generations;
+        }
+
+        for change in &frame.changes {
+            self.cells[change.offset] = change.after;
+        }
+        self.sync_universe(frame.changes.iter().map(|change| change.offset));
+        self.history.clear();
+    }
+
+    /// Copies the cells at the offsets into the universe of an unbounded grid
+    fn sync_universe(&mut self, offsets: impl IntoIterator<Item = usize>) {
+        if let Some((universe, origin)) = self.universe.as_mut() {
+            for offset in offsets {
+                let position = origin.offset(Index::from_offset(offset, self.width));
+                universe.set(position, self.cells[offset].alive);
+            }
+        }
+    }
+
+    /// Tells whether the grid is still evolving, judging by the generations computed since it was
+    /// last edited
+    pub fn stability(&self) -> Stability {
+        self.history.stability()
+    }
+
+    /// Handles the grid according to the policy for its current stability. Returns whether the
+    /// cells of the grid were changed.
+    pub fn apply_policy(&mut self, policy: &StabilityPolicy) -> bool {
+        match policy.policy_for(self.stability()) {
+            Policy::Continue => false,
+            Policy::Stop => {
+                self.stop();
+                false
+            }
+            Policy::Reseed => {
+                self.reseed(OsRng.gen());
+                true
+            }
+            Policy::InjectNoise => {
+                self.inject_noise(NOISE_DENSITY);
+                true
+            }
+        }
+    }
+
+    /// Fills the grid with a new soup from the `seed`. The density and symmetry of the previous
+    /// soup are kept, if there was one.
+    pub fn reseed(&mut self, seed: u64) {
+        let soup = match self.soup {
+            Some(soup) => Soup { seed, ..soup },
+            None => Soup::new(seed, DEFAULT_DENSITY),
+        };
+
+        let cells = soup.generate(self.width, self.height);
+        let _ = self.set_cells(cells.into_iter().enumerate());
+        self.soup = Some(soup);
+    }
+
+    /// Flips the `density` fraction of randomly chosen cells, but always at least one
+    pub fn inject_noise(&mut self, density: f64) {
+        let length = self.cells.len();
+        if length == 0 {
+            return;
+        }
+        let amount = ((length as f64 * density.max(0.0).min(1.0)) as usize).max(1);
+
+        let flipped: Vec<(usize, bool)> = index::sample(&mut OsRng, length, amount)
+            .into_iter()
+            .map(|idx| (idx, !self.cells[idx].alive))
+            .collect();
+        let _ = self.set_cells(flipped);
+    }
+
     pub fn iter(&self) -> std::slice::Iter<Cell> {
         self.cells.iter()
     }
 
-    pub fn count_neighbors<I: Into<Index>>(&self, index: I) -> usize {
+    pub fn count_neighbors(&self, index: Index) -> usize {
         // The neighbor in the case of a grid is only an alive cell
-        let index = index.into();
         let mut neighbor_counter: usize = 0;
 
-        index.neighbors().iter().for_each(|neighbor_idx| {
-            neighbor_counter += self.cells[usize::from(*neighbor_idx)].alive as usize
-        });
+        index
+            .neighbors(self.width, self.height, self.topology)
+            .iter()
+            .for_each(|neighbor_idx| {
+                neighbor_counter += self.cells[neighbor_idx.to_offset(self.width)].alive as usize
+            });
 
         neighbor_counter
     }
@@ -127,18 +626,12 @@ impl Grid {
 
     pub fn change_cell<I: Into<usize>>(&mut self, index: I) -> Result<()> {
         let index = index.into();
+        let alive = self
+            .get_cell(index)
+            .ok_or_else(|| GameError::IndexOutOfBounds(Index::from_offset(index, self.width)))?
+            .alive;
 
-        let cell = self
-            .cells
-            .get_mut(index)
-            .ok_or_else(|| GameError::IndexOutOfBounds(index.into()))?;
-
-        *cell = Cell {
-            alive: !cell.alive,
-            just_changed: true,
-        };
-
-        Ok(())
+        self.set_cell(index, !alive)
     }
 
     pub fn get_cell<I: Into<usize>>(&self, index: I) -> Option<&Cell> {
@@ -147,17 +640,98 @@ impl Grid {
     }
 
     pub fn set_cell<I: Into<usize>>(&mut self, index: I, value: bool) -> Result<()> {
-        let index = index.into();
+        self.set_cells(Some((index.into(), value)))
+    }
 
-        let cell = self
-            .cells
-            .get_mut(index)
-            .ok_or_else(|| GameError::IndexOutOfBounds(index.into()))?;
+    /// Sets the cells at the offsets to the values. All the changes are a single edit, which can
+    /// be undone at once. Nothing is changed if any of the offsets is out of bounds.
+    pub fn set_cells(&mut self, cells: impl IntoIterator<Item = (usize, bool)>) -> Result<()> {
+        let cells: Vec<(usize, bool)> = cells.into_iter().collect();
+        if let Some(&(index, _)) = cells.iter().find(|(index, _)| *index >= self.cells.len()) {
+            return Err(GameError::IndexOutOfBounds(Index::from_offset(
+                index, self.width,
+            )));
+        }
+
+        let mut changes = Vec::new();
+        for (offset, value) in cells {
+            let before = self.cells[offset];
+            let after = if value == before.alive && before.decay == 0 {
+                Cell {
+                    just_changed: false,
+                    ..before
+                }
+            } else {
+                Cell {
+                    alive: value,
+                    just_changed: value != before.alive,
+                    ..Default::default()
+                }
+            };
+
+            self.cells[offset] = after;
+            if before.alive != after.alive || before.decay != after.decay {
+                changes.push(Change {
+                    offset,
+                    before,
+                    after,
+                });
+            }
+        }
+
+        if !changes.is_empty() {
+            self.sync_universe(changes.iter().map(|change| change.offset));
+            self.timeline.push(Frame {
+                kind: FrameKind::Edit,
+                generations: 0,
+                changes,
+            });
+            self.history.clear();
+        }
+
+        Ok(())
+    }
 
-        *cell = Cell {
-            alive: value,
-            just_changed: value != cell.alive,
+    /// Stamps the pattern onto the grid with its top left corner at `offset`. The cells covered
+    /// by the pattern are replaced, the rest of the grid stays intact. If the pattern specifies a
+    /// rule, the grid switches to it.
+    pub fn load_pattern(&mut self, pattern: &Pattern, offset: Index) -> Result<()> {
+        let too_large = || GameError::PatternTooLarge {
+            width: pattern.width,
+            height: pattern.height,
         };
+        let size = pattern
+            .width
+            .checked_mul(pattern.height)
+            .ok_or_else(too_large)?;
+        let bottom = offset
+            .row
+            .checked_add(pattern.height)
+            .ok_or_else(too_large)?;
+        let right = offset
+            .col
+            .checked_add(pattern.width)
+            .ok_or_else(too_large)?;
+        if bottom > self.height() || right > self.width() {
+            return Err(too_large());
+        }
+
+        let mut alive = vec![false; size];
+        for index in &pattern.cells {
+            if index.row < pattern.height && index.col < pattern.width {
+                alive[index.to_offset(pattern.width)] = true;
+            }
+        }
+        let width = self.width();
+        self.set_cells(alive.into_iter().enumerate().map(|(idx, alive)| {
+            let index = Index::from_offset(idx, pattern.width);
+            let index = Index::new(offset.row + index.row, offset.col + index.col);
+            (index.to_offset(width), alive)
+        }))?;
+
+        if let Some(rule) = pattern.rule {
+            self.set_rule(rule);
+        }
 
         Ok(())
     }
@@ -184,7 +758,7 @@ impl Grid {
 
             for row in index_start.row..index_end.row {
                 for col in index_start.col..index_end.col {
-                    let idx = row * GRID_WIDTH + col;
+                    let idx = Index::new(row, col).to_offset(self.width);
                     if self.cells[idx].alive {
                         volume_value += self.cells[idx].just_changed as u32;
                     } else {
@@ -214,13 +788,15 @@ impl Grid {
         (pitch_value, volume_value)
     }
 
-    /// Returns subgrids for the current grid
-    fn subgrids() -> [(Index, Index); NUMBER_OF_SUBGRIDS] {
+    /// Returns subgrids for a grid of the given size. The start index of a subgrid is inclusive
+    /// and the end index is exclusive. The last row and column of subgrids take over the
+    /// remainder when the size is not divisible by the number of subgrids.
+    fn subgrids(width: usize, height: usize) -> [(Index, Index); NUMBER_OF_SUBGRIDS] {
         let mut subgrids: [(Index, Index); NUMBER_OF_SUBGRIDS] =
-            [(0usize.into(), 0usize.into()); NUMBER_OF_SUBGRIDS];
+            [(Index::new(0, 0), Index::new(0, 0)); NUMBER_OF_SUBGRIDS];
         let n = (NUMBER_OF_SUBGRIDS as f32).sqrt() as usize;
-        let subgrid_rows_number: usize = GRID_HEIGHT / n;
-        let subgrid_cols_number: usize = GRID_WIDTH / n;
+        let subgrid_rows_number: usize = height / n;
+        let subgrid_cols_number: usize = width / n;
 
         for c in 0..n {
             for r in 0..n {
@@ -230,8 +806,16 @@ impl Grid {
                 };
 
                 let index_end = Index {
-                    row: (r + 1) * subgrid_rows_number - 1,
-                    col: (c + 1) * subgrid_cols_number - 1,
+                    row: if r + 1 == n {
+                        height
+                    } else {
+                        (r + 1) * subgrid_rows_number
+                    },
+                    col: if c + 1 == n {
+                        width
+                    } else {
+                        (c + 1) * subgrid_cols_number
+                    },
                 };
 
                 subgrids[c * n + r] = (index_start, index_end);
@@ -262,6 +846,13 @@ mod test {
     }
 
     use super::*;
+    use crate::conway::timeline::TIMELINE_LENGTH;
+
+    fn alive(grid: &Grid, row: usize, col: usize) -> bool {
+        grid.get_cell(Index::new(row, col).to_offset(grid.width()))
+            .unwrap()
+            .alive
+    }
 
     #[test]
     fn not_all_trues() {
@@ -269,12 +860,13 @@ mod test {
         let true_count = grid
             .iter()
             .fold(0, |acc, &cell| if cell.alive { acc + 1 } else { acc });
-        assert_ne!(true_count, GRID_HEIGHT * GRID_HEIGHT);
+        assert_ne!(true_count, GRID_WIDTH * GRID_HEIGHT);
     }
 
     #[test]
     fn board_changes() {
-        let mut grid = Grid::random();
+        let mut grid = Grid::random_with(2020, DEFAULT_DENSITY);
+        grid.start();
 
         let old_grid = grid.clone();
         grid.next_gen();
@@ -300,7 +892,8 @@ mod test {
 
     #[test]
     fn some_cells_died() {
-        let mut grid = Grid::random();
+        let mut grid = Grid::random_with(2020, DEFAULT_DENSITY);
+        grid.start();
         let now = Instant::now();
         grid.next_gen();
         let mut deaths_in_subgrids = grid.get_pitch_and_volume_per_subgrid();
@@ -308,4 +901,390 @@ mod test {
         println!("{}", now);
         assert!(!deaths_in_subgrids.any(|&(pitch, volume)| pitch == 0 && volume == 0));
     }
+
+    #[test]
+    fn recreate_from_soup() {
+        let grid = Grid::random_with(7, 0.3);
+        assert_eq!(grid.soup(), Some(Soup::new(7, 0.3)));
+        assert_eq!(
+            Grid::from_soup(grid.soup().unwrap(), grid.width(), grid.height()),
+            grid
+        );
+        assert_ne!(Grid::random_with(8, 0.3), grid);
+
+        let random = Grid::random_with_size(20, 10);
+        let soup = random.soup().unwrap();
+        assert_eq!(soup.density, DEFAULT_DENSITY);
+        assert_eq!(Grid::from_soup(soup, 20, 10), random);
+        assert_eq!(Grid::empty().soup(), None);
+    }
+
+    #[test]
+    fn stability() {
+        let mut grid = Grid::with_pattern("Block", Index::new(5, 5)).unwrap();
+        grid.start();
+        assert_eq!(grid.stability(), Stability::Evolving);
+        grid.next_gen();
+        assert_eq!(grid.stability(), Stability::Static);
+
+        let mut grid = Grid::with_pattern("Pulsar", Index::new(5, 5)).unwrap();
+        grid.start();
+        for _ in 0..2 {
+            grid.next_gen();
+            assert_eq!(grid.stability(), Stability::Evolving);
+        }
+        grid.next_gen();
+        assert_eq!(grid.stability(), Stability::Periodic(3));
+
+        // editing the grid forgets the history
+        grid.change_cell(0usize).unwrap();
+        assert_eq!(grid.stability(), Stability::Evolving);
+
+        let mut grid = Grid::empty();
+        grid.set_cell(0usize, true).unwrap();
+        grid.start();
+        grid.next_gen();
+        assert_eq!(grid.stability(), Stability::Empty);
+    }
+
+    #[test]
+    fn policies() {
+        let policy = StabilityPolicy {
+            empty: Policy::Reseed,
+            still: Policy::Stop,
+            periodic: Policy::InjectNoise,
+        };
+
+        let mut grid = Grid::with_pattern("Block", Index::new(5, 5)).unwrap();
+        grid.start();
+        grid.next_gen();
+        grid.apply_policy(&policy);
+        assert!(grid.stopped);
+
+        let mut grid = Grid::with_pattern("Blinker", Index::new(5, 5)).unwrap();
+        grid.start();
+        grid.next_gen();
+        grid.apply_policy(&policy);
+        assert_eq!(grid.count_ones(), 3);
+        grid.next_gen();
+        let before = grid.clone();
+        grid.apply_policy(&policy);
+        let flipped = grid
+            .iter()
+            .zip(before.iter())
+            .filter(|(cell, old)| cell.alive != old.alive)
+            .count();
+        assert_eq!(flipped, (GRID_WIDTH * GRID_HEIGHT) / 50);
+        assert_eq!(grid.stability(), Stability::Evolving);
+
+        let mut grid = Grid::random_with(1, 0.3);
+        grid.reseed(2);
+        assert_eq!(grid, Grid::random_with(2, 0.3));
+        assert_eq!(grid.soup(), Some(Soup::new(2, 0.3)));
+
+        let mut grid = Grid::empty();
+        grid.start();
+        grid.next_gen();
+        grid.apply_policy(&policy);
+        assert_ne!(grid.count_ones(), 0);
+        assert!(grid.soup().is_some());
+    }
+
+    #[test]
+    fn brians_brain() {
+        // two neighboring cells give birth to the cells above and below them, while they start
+        // dying themselves
+        let mut grid = Grid::empty_with_size(6, 6);
+        grid.set_rule(Rule::brians_brain());
+        grid.set_cell(Index::new(2, 2).to_offset(6), true).unwrap();
+        grid.set_cell(Index::new(2, 3).to_offset(6), true).unwrap();
+        grid.start();
+
+        grid.next_gen();
+        let dying = |grid: &Grid| grid.iter().filter(|cell| cell.decay > 0).count();
+        assert_eq!(grid.count_ones(), 4);
+        assert_eq!(dying(&grid), 2);
+        assert!(!alive(&grid, 2, 2) && !alive(&grid, 2, 3));
+
+        // the dying cells don't count as neighbors, so the cells next to them are born
+        grid.next_gen();
+        assert_eq!(grid.count_ones(), 6);
+        assert!(alive(&grid, 2, 1) && alive(&grid, 2, 4));
+        assert_eq!(dying(&grid), 4);
+        assert_eq!(grid.iter().filter(|cell| cell.decay > 1).count(), 0);
+    }
+
+    #[test]
+    fn cell_ages() {
+        let mut grid = Grid::with_pattern("Blinker", Index::new(5, 5)).unwrap();
+        grid.start();
+        for _ in 0..4 {
+            grid.next_gen();
+        }
+        let center = grid
+            .get_cell(Index::new(5, 6).to_offset(GRID_WIDTH))
+            .unwrap();
+        assert!(center.alive);
+        assert_eq!(center.age, 4);
+        let tip = grid
+            .get_cell(Index::new(5, 5).to_offset(GRID_WIDTH))
+            .unwrap();
+        assert_eq!(tip.age, 0);
+
+        // setting a cell to its current state doesn't make it younger
+        grid.set_cell(Index::new(5, 6).to_offset(GRID_WIDTH), true)
+            .unwrap();
+        let center = grid
+            .get_cell(Index::new(5, 6).to_offset(GRID_WIDTH))
+            .unwrap();
+        assert_eq!((center.age, center.just_changed), (4, false));
+    }
+
+    #[test]
+    fn step_back_and_forward() {
+        let mut grid = Grid::random_with(5, DEFAULT_DENSITY);
+        grid.start();
+        let mut generations = vec![grid.clone()];
+        for _ in 0..10 {
+            grid.next_gen();
+            generations.push(grid.clone());
+        }
+        assert_eq!(grid.generation(), 10);
+
+        for generation in (0..10).rev() {
+            assert!(grid.step_back());
+            assert_eq!(grid.generation(), generation as u64);
+            let expected = &generations[generation];
+            assert!(grid.iter().zip(expected.iter()).all(|(cell, expected)| {
+                cell.alive == expected.alive
+                    && cell.age == expected.age
+                    && cell.just_changed == expected.just_changed
+            }));
+        }
+        assert!(!grid.step_back());
+        assert_eq!(grid.newest_generation(), 10);
+
+        grid.seek(7).unwrap();
+        assert_eq!(grid, generations[7]);
+        grid.seek(12).unwrap();
+        assert_eq!(grid.newest_generation(), 12);
+        grid.seek(3).unwrap();
+        assert_eq!(grid, generations[3]);
+    }
+
+    #[test]
+    fn bounded_history() {
+        let mut grid = Grid::random_with(5, DEFAULT_DENSITY);
+        grid.start();
+        for _ in 0..TIMELINE_LENGTH + 10 {
+            grid.next_gen();
+        }
+        assert_eq!(grid.oldest_generation(), 10);
+        match grid.seek(9) {
+            Err(GameError::GenerationNotInHistory(generation)) => assert_eq!(generation, 9),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_edits() {
+        let mut grid = Grid::empty_with_size(10, 10);
+        grid.set_cell(0usize, true).unwrap();
+        grid.change_cell(1usize).unwrap();
+        grid.load_pattern(&library::find("Block").unwrap().pattern(), Index::new(5, 5))
+            .unwrap();
+        assert_eq!(grid.count_ones(), 6);
+
+        // loading the pattern is a single edit
+        assert!(grid.undo());
+        assert_eq!(grid.count_ones(), 2);
+        assert!(grid.undo());
+        assert!(grid.undo());
+        assert_eq!(grid.count_ones(), 0);
+        assert!(!grid.undo());
+
+        assert!(grid.redo());
+        assert!(alive(&grid, 0, 0));
+        assert!(grid.redo());
+        assert_eq!(grid.count_ones(), 2);
+
+        // a new edit drops the undone ones
+        grid.set_cell(2usize, true).unwrap();
+        assert!(!grid.redo());
+        assert_eq!(grid.count_ones(), 3);
+
+        // edits made before a generation are reverted by stepping back, not by undo
+        grid.start();
+        grid.next_gen();
+        assert!(!grid.undo());
+        assert!(grid.step_back());
+        assert!(grid.undo());
+        assert_eq!(grid.count_ones(), 2);
+    }
+
+    #[test]
+    fn generation_delta() {
+        let mut grid = Grid::with_pattern("Blinker", Index::new(5, 5)).unwrap();
+        assert_eq!(grid.next_gen_with_delta(), None);
+
+        grid.start();
+        let delta = grid.next_gen_with_delta().unwrap();
+        assert_eq!(delta.born, vec![Index::new(4, 6), Index::new(6, 6)]);
+        assert_eq!(delta.died, vec![Index::new(5, 5), Index::new(5, 7)]);
+        assert_eq!(delta.centroid(), Some((5.0, 6.0)));
+
+        // dying cells of Generations rules are neither born nor dead
+        let mut grid = Grid::empty_with_size(6, 6);
+        grid.set_rule(Rule::star_wars());
+        grid.set_cell(0usize, true).unwrap();
+        grid.start();
+        assert_eq!(
+            grid.next_gen_with_delta().unwrap().died,
+            vec![Index::new(0, 0)]
+        );
+        let delta = grid.next_gen_with_delta().unwrap();
+        assert!(delta.is_empty());
+        assert_eq!(delta.decayed, vec![Index::new(0, 0)]);
+    }
+
+    #[test]
+    fn custom_size() {
+        let grid = Grid::empty_with_size(200, 120);
+        assert_eq!(grid.width(), 200);
+        assert_eq!(grid.height(), 120);
+        assert_eq!(grid.iter().count(), 200 * 120);
+
+        let grid = Grid::random_with_size(16, 16);
+        assert_eq!(grid.iter().count(), 16 * 16);
+    }
+
+    #[test]
+    fn blinker_in_non_square_grid() {
+        let width = 7;
+        let mut grid = Grid::empty_with_size(width, 3);
+        for col in 2..5 {
+            grid.set_cell(Index::new(1, col).to_offset(width), true)
+                .unwrap();
+        }
+        grid.start();
+
+        grid.next_gen();
+        assert_eq!(grid.count_ones(), 3);
+        for row in 0..3 {
+            assert!(alive(&grid, row, 3));
+        }
+
+        grid.next_gen();
+        for col in 2..5 {
+            assert!(alive(&grid, 1, col));
+        }
+    }
+
+    #[test]
+    fn out_of_bounds_cell() {
+        let mut grid = Grid::empty_with_size(16, 16);
+        assert!(grid.get_cell(16usize * 16).is_none());
+        assert!(grid.set_cell(16usize * 16, true).is_err());
+        assert!(grid.change_cell(16usize * 16).is_err());
+    }
+
+    #[test]
+    fn seeds_rule() {
+        let width = 6;
+        let mut grid = Grid::empty_with_size(width, 6);
+        grid.set_rule("B2/S".parse().unwrap());
+        grid.set_cell(Index::new(2, 2).to_offset(width), true)
+            .unwrap();
+        grid.set_cell(Index::new(2, 3).to_offset(width), true)
+            .unwrap();
+        grid.start();
+
+        grid.next_gen();
+        // under Seeds every alive cell dies, the domino gives birth to two pairs of cells
+        assert_eq!(grid.count_ones(), 4);
+        assert!(!alive(&grid, 2, 2));
+        assert!(alive(&grid, 1, 2));
+        assert!(alive(&grid, 3, 3));
+    }
+
+    #[test]
+    fn glider_crosses_torus_edge() {
+        let mut grid = Grid::empty_with_size(8, 8);
+        grid.set_topology(Topology::Torus);
+        for &(row, col) in &[(5, 6), (6, 7), (7, 5), (7, 6), (7, 7)] {
+            grid.set_cell(Index::new(row, col).to_offset(8), true)
+                .unwrap();
+        }
+        grid.start();
+
+        // after 4 generations the glider moves by one cell diagonally
+        for _ in 0..4 {
+            grid.next_gen();
+        }
+        assert_eq!(grid.count_ones(), 5);
+        for &(row, col) in &[(6, 7), (7, 0), (0, 6), (0, 7), (0, 0)] {
+            assert!(alive(&grid, row, col));
+        }
+
+        // a whole lap around the torus brings it back
+        for _ in 0..28 {
+            grid.next_gen();
+        }
+        for &(row, col) in &[(5, 6), (6, 7), (7, 5), (7, 6), (7, 7)] {
+            assert!(alive(&grid, row, col));
+        }
+    }
+
+    #[test]
+    fn unbounded_glider() {
+        let mut grid = Grid::empty_with_size(8, 8);
+        grid.set_unbounded(true).unwrap();
+        for &(row, col) in &[(5, 6), (6, 7), (7, 5), (7, 6), (7, 7)] {
+            grid.set_cell(Index::new(row, col).to_offset(8), true)
+                .unwrap();
+        }
+        grid.start();
+
+        // the glider leaves the window and keeps travelling outside of it
+        for _ in 0..16 {
+            grid.next_gen();
+        }
+        assert_eq!(grid.count_ones(), 0);
+
+        grid.center_view();
+        assert_eq!(grid.count_ones(), 5);
+        for &(row, col) in &[(3, 4), (4, 5), (5, 3), (5, 4), (5, 5)] {
+            assert!(alive(&grid, row, col));
+        }
+        for _ in 0..4 {
+            grid.next_gen();
+        }
+        for &(row, col) in &[(4, 5), (5, 6), (6, 4), (6, 5), (6, 6)] {
+            assert!(alive(&grid, row, col));
+        }
+
+        // edits of the window reach the universe
+        grid.set_cell(Index::new(5, 6).to_offset(8), false).unwrap();
+        grid.set_cell(Index::new(0, 0).to_offset(8), true).unwrap();
+        grid.next_gen();
+        assert!(!alive(&grid, 0, 0));
+
+        grid.set_unbounded(false).unwrap();
+        assert!(!grid.is_unbounded());
+    }
+
+    #[test]
+    fn unbounded_rules() {
+        let mut grid = Grid::empty_with_size(8, 8);
+        grid.set_rule(Rule::brians_brain());
+        assert!(grid.set_unbounded(true).is_err());
+        assert!(!grid.is_unbounded());
+
+        grid.set_rule(Rule::conway());
+        grid.set_unbounded(true).unwrap();
+        grid.set_rule(Rule::highlife());
+        assert!(grid.is_unbounded());
+        grid.set_rule(Rule::star_wars());
+        assert!(!grid.is_unbounded());
+    }
 }
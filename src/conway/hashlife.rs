@@ -0,0 +1,633 @@
+//! HashLife engine, which fast-forwards the contents of a grid by 2^k generations.
+//!
+//! The cells are stored in a quadtree. Identical subtrees are stored only once and the future
+//! of every subtree is memoized, so repetitive patterns can be advanced by thousands of
+//! generations in a fraction of the time the regular engines would need.
+//!
+//! The grid is treated as a window onto an unbounded plane, so only bounded grids are supported,
+//! and not the ones which already are windows onto a universe. Cells which end up outside of the
+//! window after advancing are dropped. Rules with dying states
+//! and rules in which cells are born without any alive neighbors are not supported either.
+
+use std::collections::HashMap;
+
+use super::grid::Grid;
+use super::index::Index;
+use super::rule::Rule;
+use super::topology::Topology;
+use super::{GameError, Result};
+
+/// Default maximal number of nodes and memoized results kept in the cache
+pub const DEFAULT_CACHE_LIMIT: usize = 1 << 20;
+
+/// Highest power of two of the generations a grid can be advanced by, the positions of the cells
+/// in the bigger nodes wouldn't fit into 64 bits
+pub const MAX_LOG2_GENERATIONS: u8 = 60;
+
+type NodeId = usize;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// Four children of a node, each of them one level lower than the node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Quad {
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+}
+
+/// Square of 2^level x 2^level cells. Nodes of level 0 are single cells.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    level: u8,
+    population: u64,
+    quad: Quad,
+}
+
+pub struct HashLife {
+    cache_limit: usize,
+    rule: Rule,
+    nodes: Vec<Node>,
+    lookup: HashMap<Quad, NodeId>,
+    results: HashMap<(NodeId, u8), NodeId>,
+    empty: Vec<NodeId>,
+}
+
+impl HashLife {
+    /// Creates the engine. The cache is cleared whenever it holds more than `cache_limit` nodes
+    /// and memoized results, even in the middle of an advance.
+    pub fn new(cache_limit: usize) -> Self {
+        let mut hashlife = Self {
+            cache_limit,
+            rule: Rule::default(),
+            nodes: Vec::new(),
+            lookup: HashMap::new(),
+            results: HashMap::new(),
+            empty: Vec::new(),
+        };
+        hashlife.clear();
+        hashlife
+    }
+
+    /// Number of nodes and memoized results currently held in the cache
+    pub fn cache_size(&self) -> usize {
+        self.nodes.len() + self.results.len()
+    }
+
+    fn is_full(&self) -> bool {
+        self.cache_size() > self.cache_limit
+    }
+
+    /// Drops all the nodes and memoized results
+    pub fn clear(&mut self) {
+        let leaf = |population| Node {
+            level: 0,
+            population,
+            quad: Quad {
+                nw: DEAD,
+                ne: DEAD,
+                sw: DEAD,
+                se: DEAD,
+            },
+        };
+        self.nodes = vec![leaf(0), leaf(1)];
+        self.lookup.clear();
+        self.results.clear();
+        self.empty = vec![DEAD];
+    }
+
+    /// Advances the `grid` by 2^`log2_generations` generations under the rule of the grid. The
+    /// leap is a single frame of the timeline of the grid.
+    pub fn advance(&mut self, grid: &mut Grid, log2_generations: u8) -> Result<()> {
+        Self::check(grid, log2_generations)?;
+
+        if self.is_full() || self.rule != grid.rule() {
+            self.clear();
+            self.rule = grid.rule();
+        }
+
+        let width = grid.width();
+        let mut alive: Vec<(i64, i64)> = grid
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.alive)
+            .map(|(idx, _)| ((idx / width) as i64, (idx % width) as i64))
+            .collect();
+
+        // when the cache fills up, it is rebuilt from the cells and the rest of the generations
+        // are computed in smaller leaps. A single generation is computed even if it doesn't fit.
+        let mut log2 = log2_generations;
+        let mut remaining: u64 = 1 << log2_generations;
+        while remaining > 0 {
+            match self.advance_cells(&alive, log2) {
+                Some(next) => {
+                    alive = next;
+                    remaining -= 1 << log2;
+                }
+                None if log2 > 0 => {
+                    self.clear();
+                    log2 -= 1;
+                }
+                None => {
+                    self.clear();
+                    let limit = std::mem::replace(&mut self.cache_limit, usize::MAX);
+                    alive = self.advance_cells(&alive, 0).unwrap_or_default();
+                    self.cache_limit = limit;
+                    remaining -= 1;
+                }
+            }
+        }
+        if self.is_full() {
+            self.clear();
+        }
+
+        let mut next_alive = vec![false; width * grid.height()];
+        for (row, col) in alive {
+            if row >= 0 && col >= 0 && (row as usize) < grid.height() && (col as usize) < width {
+                next_alive[Index::new(row as usize, col as usize).to_offset(width)] = true;
+            }
+        }
+        grid.leap(next_alive, 1 << log2_generations);
+
+        Ok(())
+    }
+
+    /// Returns the `alive` cells advanced by 2^`log2_generations` generations, none if the cache
+    /// filled up in the meantime
+    fn advance_cells(
+        &mut self,
+        alive: &[(i64, i64)],
+        log2_generations: u8,
+    ) -> Option<Vec<(i64, i64)>> {
+        if alive.is_empty() {
+            return Some(Vec::new());
+        }
+        let min_row = alive.iter().map(|&(row, _)| row).min()?;
+        let min_col = alive.iter().map(|&(_, col)| col).min()?;
+        let max_row = alive.iter().map(|&(row, _)| row).max()?;
+        let max_col = alive.iter().map(|&(_, col)| col).max()?;
+
+        let size = (max_row - min_row).max(max_col - min_col) + 1;
+        // the root has to have children, so it can be expanded
+        let mut level = 1;
+        while 1 << level < size {
+            level += 1;
+        }
+
+        let mut root = self.build(alive, level, min_row, min_col);
+        // position of the top left corner of the root
+        let (mut row, mut col) = (min_row, min_col);
+
+        while self.nodes[root].level < log2_generations + 2 || !self.is_padded(root) {
+            let half = 1 << (self.nodes[root].level - 1);
+            row -= half;
+            col -= half;
+            root = self.expand(root);
+        }
+        let half = 1 << (self.nodes[root].level - 1);
+        row -= half;
+        col -= half;
+        root = self.expand(root);
+
+        let level = self.nodes[root].level;
+        let result = self.step(root, log2_generations)?;
+        let quarter = 1 << (level - 2);
+
+        let mut next = Vec::new();
+        self.collect_alive(result, row + quarter, col + quarter, &mut next);
+        Some(next)
+    }
+
+    /// Checks whether the grid can be advanced by 2^`log2_generations` generations
+    fn check(grid: &Grid, log2_generations: u8) -> Result<()> {
+        if grid.topology() != Topology::Bounded {
+            return Err(GameError::UnsupportedTopology(grid.topology()));
+        }
+        if grid.is_unbounded() {
+            return Err(GameError::Unbounded);
+        }
+        if grid.rule().states() > 2 || grid.rule().is_born(0) {
+            return Err(GameError::UnsupportedRule(grid.rule().to_string()));
+        }
+        if log2_generations > MAX_LOG2_GENERATIONS {
+            return Err(GameError::TooManyGenerations(log2_generations));
+        }
+        Ok(())
+    }
+
+    /// Builds the node of the `level` with the top left corner at (`row`, `col`) out of the
+    /// `alive` cells lying in it
+    fn build(&mut self, alive: &[(i64, i64)], level: u8, row: i64, col: i64) -> NodeId {
+        if alive.is_empty() {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return ALIVE;
+        }
+
+        let half = 1 << (level - 1);
+        let (north, south): (Vec<_>, Vec<_>) = alive.iter().partition(|&&(r, _)| r < row + half);
+        let (nw, ne): (Vec<_>, Vec<_>) = north.into_iter().partition(|&(_, c)| c < col + half);
+        let (sw, se): (Vec<_>, Vec<_>) = south.into_iter().partition(|&(_, c)| c < col + half);
+        let nw = self.build(&nw, level - 1, row, col);
+        let ne = self.build(&ne, level - 1, row, col + half);
+        let sw = self.build(&sw, level - 1, row + half, col);
+        let se = self.build(&se, level - 1, row + half, col + half);
+        self.join(nw, ne, sw, se)
+    }
+
+    fn collect_alive(&self, node: NodeId, row: i64, col: i64, alive: &mut Vec<(i64, i64)>) {
+        let Node {
+            level,
+            population,
+            quad,
+        } = self.nodes[node];
+
+        if population == 0 {
+            return;
+        }
+        if level == 0 {
+            alive.push((row, col));
+            return;
+        }
+
+        let half = 1 << (level - 1);
+        self.collect_alive(quad.nw, row, col, alive);
+        self.collect_alive(quad.ne, row, col + half, alive);
+        self.collect_alive(quad.sw, row + half, col, alive);
+        self.collect_alive(quad.se, row + half, col + half, alive);
+    }
+
+    /// Returns the hash-consed node made of the four children
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        let quad = Quad { nw, ne, sw, se };
+        if let Some(&id) = self.lookup.get(&quad) {
+            return id;
+        }
+
+        let population = [nw, ne, sw, se].iter().fold(0u64, |acc, &child| {
+            acc.saturating_add(self.nodes[child].population)
+        });
+        let node = Node {
+            level: self.nodes[nw].level + 1,
+            population,
+            quad,
+        };
+
+        let id = self.nodes.len();
+        self.nodes.push(node);
+        self.lookup.insert(quad, id);
+        id
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let next = self.join(e, e, e, e);
+            self.empty.push(next);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Surrounds the node with empty cells, so it becomes the center of a node one level higher
+    fn expand(&mut self, node: NodeId) -> NodeId {
+        let Node { level, quad, .. } = self.nodes[node];
+        let e = self.empty(level - 1);
+        let nw = self.join(e, e, e, quad.nw);
+        let ne = self.join(e, e, quad.ne, e);
+        let sw = self.join(e, quad.sw, e, e);
+        let se = self.join(quad.se, e, e, e);
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Whether all the alive cells of the node lie in its center
+    fn is_padded(&self, node: NodeId) -> bool {
+        let Node { level, quad, .. } = self.nodes[node];
+        if level < 2 {
+            return self.nodes[node].population == 0;
+        }
+
+        let nw = self.nodes[quad.nw].quad;
+        let ne = self.nodes[quad.ne].quad;
+        let sw = self.nodes[quad.sw].quad;
+        let se = self.nodes[quad.se].quad;
+
+        [
+            nw.nw, nw.ne, nw.sw, ne.nw, ne.ne, ne.se, sw.nw, sw.sw, sw.se, se.ne, se.sw, se.se,
+        ]
+        .iter()
+        .all(|&child| self.nodes[child].population == 0)
+    }
+
+    /// Returns the center of the node, which is one level lower
+    fn center(&mut self, node: NodeId) -> NodeId {
+        let quad = self.nodes[node].quad;
+        let nw = self.nodes[quad.nw].quad.se;
+        let ne = self.nodes[quad.ne].quad.sw;
+        let sw = self.nodes[quad.sw].quad.ne;
+        let se = self.nodes[quad.se].quad.nw;
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Returns the center of the node, which is one level lower, advanced by 2^`log2_generations`
+    /// generations. The `log2_generations` can't exceed the level of the node minus 2. Returns
+    /// none once the cache is full.
+    fn step(&mut self, node: NodeId, log2_generations: u8) -> Option<NodeId> {
+        if self.nodes[node].population == 0 {
+            let level = self.nodes[node].level;
+            return Some(self.empty(level - 1));
+        }
+        if let Some(&result) = self.results.get(&(node, log2_generations)) {
+            return Some(result);
+        }
+        if self.is_full() {
+            return None;
+        }
+
+        let Node { level, quad, .. } = self.nodes[node];
+        let result = if level == 2 {
+            self.step_base(node)
+        } else {
+            let nw = self.nodes[quad.nw].quad;
+            let ne = self.nodes[quad.ne].quad;
+            let sw = self.nodes[quad.sw].quad;
+            let se = self.nodes[quad.se].quad;
+
+            // nine overlapping nodes one level lower
+            let n00 = quad.nw;
+            let n01 = self.join(nw.ne, ne.nw, nw.se, ne.sw);
+            let n02 = quad.ne;
+            let n10 = self.join(nw.sw, nw.se, sw.nw, sw.ne);
+            let n11 = self.join(nw.se, ne.sw, sw.ne, se.nw);
+            let n12 = self.join(ne.sw, ne.se, se.nw, se.ne);
+            let n20 = quad.sw;
+            let n21 = self.join(sw.ne, se.nw, sw.se, se.sw);
+            let n22 = quad.se;
+            let nine = [n00, n01, n02, n10, n11, n12, n20, n21, n22];
+
+            // at full speed both halves advance by a quarter of the node, otherwise only the
+            // second one does
+            let full_speed = log2_generations == level - 2;
+            let mut r = [DEAD; 9];
+            for (r, &n) in r.iter_mut().zip(nine.iter()) {
+                *r = if full_speed {
+                    self.step(n, level - 3)?
+                } else {
+                    self.center(n)
+                };
+            }
+
+            let second = if full_speed {
+                level - 3
+            } else {
+                log2_generations
+            };
+            let c00 = self.join(r[0], r[1], r[3], r[4]);
+            let c01 = self.join(r[1], r[2], r[4], r[5]);
+            let c10 = self.join(r[3], r[4], r[6], r[7]);
+            let c11 = self.join(r[4], r[5], r[7], r[8]);
+            let nw = self.step(c00, second)?;
+            let ne = self.step(c01, second)?;
+            let sw = self.step(c10, second)?;
+            let se = self.step(c11, second)?;
+            self.join(nw, ne, sw, se)
+        };
+
+        self.results.insert((node, log2_generations), result);
+        Some(result)
+    }
+
+    /// Advances the center 2x2 cells of a 4x4 node by a single generation
+    fn step_base(&mut self, node: NodeId) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        let quad = self.nodes[node].quad;
+        for (child, row, col) in &[
+            (quad.nw, 0, 0),
+            (quad.ne, 0, 2),
+            (quad.sw, 2, 0),
+            (quad.se, 2, 2),
+        ] {
+            let leaves = self.nodes[*child].quad;
+            cells[*row][*col] = leaves.nw == ALIVE;
+            cells[*row][col + 1] = leaves.ne == ALIVE;
+            cells[row + 1][*col] = leaves.sw == ALIVE;
+            cells[row + 1][col + 1] = leaves.se == ALIVE;
+        }
+
+        let mut next = [DEAD; 4];
+        for (i, next) in next.iter_mut().enumerate() {
+            let (row, col) = (1 + i / 2, 1 + i % 2);
+            let neighbors = cells[row - 1..=row + 1]
+                .iter()
+                .flat_map(|line| &line[col - 1..=col + 1])
+                .filter(|&&alive| alive)
+                .count()
+                - cells[row][col] as usize;
+            if self.rule.next_state(cells[row][col], neighbors) {
+                *next = ALIVE;
+            }
+        }
+
+        self.join(next[0], next[1], next[2], next[3])
+    }
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn alive_cells(grid: &Grid) -> Vec<usize> {
+        grid.iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.alive)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Places a random soup in the middle of a big grid, so it never reaches the edges
+    fn soup(size: usize, soup_size: usize) -> Grid {
+        let mut grid = Grid::empty_with_size(size, size);
+        let random = Grid::random_with_size(soup_size, soup_size);
+        let offset = (size - soup_size) / 2;
+        for (idx, cell) in random.iter().enumerate() {
+            let index = Index::new(idx / soup_size + offset, idx % soup_size + offset);
+            grid.set_cell(index.to_offset(size), cell.alive).unwrap();
+        }
+        grid.start();
+        grid
+    }
+
+    #[test]
+    fn matches_regular_engine() {
+        let mut hashlife = HashLife::default();
+
+        for &k in &[0, 1, 2, 3, 5] {
+            let mut grid = soup(120, 16);
+            let mut advanced = grid.clone();
+            hashlife.advance(&mut advanced, k).unwrap();
+            for _ in 0..1 << k {
+                grid.next_gen();
+            }
+            assert_eq!(alive_cells(&advanced), alive_cells(&grid), "k = {}", k);
+        }
+    }
+
+    #[test]
+    fn matches_regular_engine_with_other_rules() {
+        let mut hashlife = HashLife::default();
+
+        for rule in &["B36/S23", "B3678/S34678"] {
+            let mut grid = soup(100, 12);
+            grid.set_rule(rule.parse().unwrap());
+            let mut advanced = grid.clone();
+            hashlife.advance(&mut advanced, 4).unwrap();
+            for _ in 0..16 {
+                grid.next_gen();
+            }
+            assert_eq!(alive_cells(&advanced), alive_cells(&grid), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn glider_moves() {
+        let width = 64;
+        let mut grid = Grid::empty_with_size(width, width);
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+        for &(row, col) in &glider {
+            grid.set_cell(Index::new(row, col).to_offset(width), true)
+                .unwrap();
+        }
+
+        // 32 generations move the glider by 8 cells diagonally
+        let mut advanced = grid.clone();
+        HashLife::default().advance(&mut advanced, 5).unwrap();
+        let expected: Vec<usize> = glider
+            .iter()
+            .map(|&(row, col)| Index::new(row + 8, col + 8).to_offset(width))
+            .collect();
+        assert_eq!(alive_cells(&advanced), expected);
+        assert!(advanced.iter().filter(|cell| cell.just_changed).count() > 0);
+
+        // the glider flies away from the window
+        let mut advanced = grid.clone();
+        HashLife::default().advance(&mut advanced, 8).unwrap();
+        assert_eq!(advanced.count_ones(), 0);
+    }
+
+    #[test]
+    fn leaps_in_the_timeline() {
+        let mut grid = soup(64, 16);
+        grid.next_gen();
+        let before = grid.clone();
+
+        HashLife::default().advance(&mut grid, 10).unwrap();
+        assert_eq!(grid.generation(), 1025);
+        assert_eq!(grid.oldest_generation(), 0);
+
+        // the leap is stepped over at once
+        assert!(grid.step_back());
+        assert_eq!(grid.generation(), 1);
+        assert_eq!(alive_cells(&grid), alive_cells(&before));
+        assert_eq!(grid.newest_generation(), 1025);
+        assert!(grid.step_forward());
+        assert_eq!(grid.generation(), 1025);
+
+        // the generations in the middle of the leap are computed again
+        let mut expected = before.clone();
+        for _ in 0..99 {
+            expected.next_gen();
+        }
+        grid.seek(100).unwrap();
+        assert_eq!(grid.generation(), 100);
+        assert_eq!(alive_cells(&grid), alive_cells(&expected));
+    }
+
+    #[test]
+    fn bounded_cache() {
+        let mut grid = soup(192, 32);
+        let mut expected = grid.clone();
+        let mut unbounded = HashLife::default();
+        unbounded.advance(&mut grid.clone(), 6).unwrap();
+        assert!(unbounded.cache_size() > 5000);
+
+        // the cache fills up in the middle of the advance, the results stay the same
+        let mut hashlife = HashLife::new(5000);
+        hashlife.advance(&mut grid, 6).unwrap();
+        assert!(hashlife.cache_size() <= 5000);
+        for _ in 0..64 {
+            expected.next_gen();
+        }
+        assert_eq!(alive_cells(&grid), alive_cells(&expected));
+
+        // a single generation is computed even if it doesn't fit into the cache
+        let mut tiny = HashLife::new(0);
+        tiny.advance(&mut grid, 2).unwrap();
+        for _ in 0..4 {
+            expected.next_gen();
+        }
+        assert_eq!(alive_cells(&grid), alive_cells(&expected));
+        assert_eq!(tiny.cache_size(), 2);
+    }
+
+    #[test]
+    fn unsupported_topologies() {
+        let mut grid = soup(16, 8);
+        for &topology in &[
+            Topology::Torus,
+            Topology::KleinBottle,
+            Topology::CrossSurface,
+            Topology::HorizontalCylinder,
+            Topology::VerticalCylinder,
+        ] {
+            grid.set_topology(topology);
+            match HashLife::default().advance(&mut grid, 2) {
+                Err(GameError::UnsupportedTopology(unsupported)) => {
+                    assert_eq!(unsupported, topology)
+                }
+                _ => panic!("{:?} shouldn't be supported", topology),
+            }
+        }
+        grid.set_topology(Topology::Bounded);
+        assert!(HashLife::default().advance(&mut grid, 2).is_ok());
+
+        grid.set_unbounded(true).unwrap();
+        match HashLife::default().advance(&mut grid, 2) {
+            Err(GameError::Unbounded) => {}
+            _ => panic!("unbounded grids shouldn't be supported"),
+        }
+    }
+
+    #[test]
+    fn too_many_generations() {
+        let mut grid = soup(16, 8);
+        for &log2_generations in &[MAX_LOG2_GENERATIONS + 1, 254, u8::MAX] {
+            match HashLife::default().advance(&mut grid, log2_generations) {
+                Err(GameError::TooManyGenerations(log2)) => assert_eq!(log2, log2_generations),
+                _ => panic!("2^{} generations shouldn't be allowed", log2_generations),
+            }
+        }
+        assert!(HashLife::default()
+            .advance(&mut grid, MAX_LOG2_GENERATIONS)
+            .is_ok());
+    }
+
+    #[test]
+    fn unsupported_rules() {
+        for rule in &[Rule::brians_brain(), "B03/S23".parse().unwrap()] {
+            let mut grid = Grid::empty_with_size(8, 8);
+            grid.set_rule(*rule);
+            match HashLife::default().advance(&mut grid, 2) {
+                Err(GameError::UnsupportedRule(name)) => assert_eq!(name, rule.to_string()),
+                _ => panic!("{} shouldn't be supported", rule),
+            }
+        }
+    }
+}
@@ -15,7 +15,7 @@
 //!        --------------------->
 //!               columns
 
-use super::grid::{GRID_HEIGHT, GRID_WIDTH};
+use super::topology::Topology;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Index {
@@ -24,7 +24,27 @@ pub struct Index {
 }
 
 impl Index {
-    pub fn neighbors(self) -> Vec<Self> {
+    pub fn new(row: usize, col: usize) -> Self {
+        Self { row, col }
+    }
+
+    /// Converts an offset into the cells of a grid with the given `width` into an index
+    pub fn from_offset(offset: usize, width: usize) -> Self {
+        Self {
+            row: offset / width,
+            col: offset % width,
+        }
+    }
+
+    /// Converts the index into an offset into the cells of a grid with the given `width`
+    pub fn to_offset(self, width: usize) -> usize {
+        self.row * width + self.col
+    }
+
+    /// Returns the neighbors of the index in a grid of the given size. The edges of the grid
+    /// are glued according to the `topology`. A cell is never its own neighbor, even if the
+    /// topology wraps around onto it.
+    pub fn neighbors(self, width: usize, height: usize, topology: Topology) -> Vec<Self> {
         let mut neighbors: Vec<Self> = Vec::with_capacity(8);
 
         for &x in &[-1, 0, 1] {
@@ -34,21 +54,12 @@ impl Index {
                 }
 
                 let row = self.row as isize + x;
-
-                if row < 0 || row >= GRID_HEIGHT as isize {
-                    continue;
-                }
-
                 let col = self.col as isize + y;
 
-                if col < 0 || col >= GRID_WIDTH as isize {
-                    continue;
+                match topology.wrap(row, col, width, height) {
+                    Some(neighbor) if neighbor != self => neighbors.push(neighbor),
+                    _ => continue,
                 }
-
-                let row = row as usize;
-                let col = col as usize;
-
-                neighbors.push(Index { row, col })
             }
         }
 
@@ -56,69 +67,147 @@ impl Index {
     }
 }
 
-impl Into<Index> for usize {
-    fn into(self) -> Index {
-        Index {
-            row: self / GRID_WIDTH,
-            col: self % GRID_WIDTH,
-        }
-    }
-}
-
-impl From<Index> for usize {
-    fn from(index: Index) -> Self {
-        index.row * GRID_WIDTH + index.col
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
 
+    const WIDTH: usize = 50;
+    const HEIGHT: usize = 50;
+
     #[test]
     fn neighbors() {
         let index = Index { row: 1, col: 1 };
-        let neighbors = index.neighbors();
+        let neighbors = index.neighbors(WIDTH, HEIGHT, Topology::Bounded);
         assert_eq!(neighbors.len(), 8);
 
         let index = Index { row: 0, col: 1 };
-        let neighbors = index.neighbors();
+        let neighbors = index.neighbors(WIDTH, HEIGHT, Topology::Bounded);
         assert_eq!(neighbors.len(), 5);
 
         let index = Index { row: 1, col: 0 };
-        let neighbors = index.neighbors();
+        let neighbors = index.neighbors(WIDTH, HEIGHT, Topology::Bounded);
         assert_eq!(neighbors.len(), 5);
 
         let index = Index { row: 0, col: 0 };
-        let neighbors = index.neighbors();
+        let neighbors = index.neighbors(WIDTH, HEIGHT, Topology::Bounded);
         assert_eq!(neighbors.len(), 3);
 
         let index = Index {
-            row: GRID_HEIGHT - 1,
+            row: HEIGHT - 1,
             col: 1,
         };
-        let neighbors = index.neighbors();
+        let neighbors = index.neighbors(WIDTH, HEIGHT, Topology::Bounded);
         assert_eq!(neighbors.len(), 5);
 
         let index = Index {
             row: 1,
-            col: GRID_WIDTH - 1,
+            col: WIDTH - 1,
         };
-        let neighbors = index.neighbors();
+        let neighbors = index.neighbors(WIDTH, HEIGHT, Topology::Bounded);
         assert_eq!(neighbors.len(), 5);
 
         let index = Index {
-            row: GRID_HEIGHT - 1,
-            col: GRID_WIDTH - 1,
+            row: HEIGHT - 1,
+            col: WIDTH - 1,
         };
-        let neighbors = index.neighbors();
+        let neighbors = index.neighbors(WIDTH, HEIGHT, Topology::Bounded);
         assert_eq!(neighbors.len(), 3);
 
         let index = Index {
-            row: GRID_HEIGHT / 2 + 2,
-            col: GRID_WIDTH / 2 + 2,
+            row: HEIGHT / 2 + 2,
+            col: WIDTH / 2 + 2,
         };
-        let neighbors = index.neighbors();
+        let neighbors = index.neighbors(WIDTH, HEIGHT, Topology::Bounded);
         assert_eq!(neighbors.len(), 8);
     }
+
+    #[test]
+    fn neighbors_in_non_square_grid() {
+        let index = Index { row: 0, col: 10 };
+        assert_eq!(index.neighbors(16, 4, Topology::Bounded).len(), 5);
+        assert_eq!(index.neighbors(11, 4, Topology::Bounded).len(), 3);
+
+        let index = Index { row: 3, col: 5 };
+        assert_eq!(index.neighbors(16, 4, Topology::Bounded).len(), 5);
+        assert_eq!(index.neighbors(16, 5, Topology::Bounded).len(), 8);
+    }
+
+    #[test]
+    fn offset_conversions() {
+        let index = Index::from_offset(37, 16);
+        assert_eq!(index, Index::new(2, 5));
+        assert_eq!(index.to_offset(16), 37);
+
+        let index = Index::from_offset(37, 200);
+        assert_eq!(index, Index::new(0, 37));
+        assert_eq!(index.to_offset(200), 37);
+    }
+
+    /// Counts the neighbors of the corners, the middles of the edges and the center of a 5x4
+    /// grid
+    fn neighbor_counts(topology: Topology) -> Vec<usize> {
+        let (width, height) = (5, 4);
+        [
+            (0, 0),
+            (0, width - 1),
+            (height - 1, 0),
+            (height - 1, width - 1),
+            (0, 2),
+            (height - 1, 2),
+            (1, 0),
+            (1, width - 1),
+            (1, 2),
+        ]
+        .iter()
+        .map(|&(row, col)| Index { row, col }.neighbors(width, height, topology).len())
+        .collect()
+    }
+
+    #[test]
+    fn neighbor_counts_per_topology() {
+        use Topology::*;
+
+        // corners, top and bottom edges, left and right edges, center
+        assert_eq!(neighbor_counts(Bounded), vec![3, 3, 3, 3, 5, 5, 5, 5, 8]);
+        assert_eq!(neighbor_counts(Torus), vec![8; 9]);
+        assert_eq!(neighbor_counts(KleinBottle), vec![8; 9]);
+        // crossing both twisted edges at a corner leads back onto the corner cell itself
+        assert_eq!(
+            neighbor_counts(CrossSurface),
+            vec![7, 7, 7, 7, 8, 8, 8, 8, 8]
+        );
+        assert_eq!(
+            neighbor_counts(HorizontalCylinder),
+            vec![5, 5, 5, 5, 5, 5, 8, 8, 8]
+        );
+        assert_eq!(
+            neighbor_counts(VerticalCylinder),
+            vec![5, 5, 5, 5, 8, 8, 5, 5, 8]
+        );
+    }
+
+    #[test]
+    fn wrapped_neighbors() {
+        let (width, height) = (5, 4);
+        let index = Index { row: 0, col: 1 };
+
+        let torus = index.neighbors(width, height, Topology::Torus);
+        assert!(torus.contains(&Index { row: 3, col: 0 }));
+        assert!(torus.contains(&Index { row: 3, col: 2 }));
+
+        // crossing the top edge of a Klein bottle mirrors the column
+        let klein = index.neighbors(width, height, Topology::KleinBottle);
+        assert!(klein.contains(&Index { row: 3, col: 4 }));
+        assert!(klein.contains(&Index { row: 3, col: 3 }));
+        assert!(klein.contains(&Index { row: 3, col: 2 }));
+        assert!(!klein.contains(&Index { row: 3, col: 0 }));
+
+        // crossing the left edge of a cross-surface mirrors the row
+        let index = Index { row: 1, col: 0 };
+        let cross = index.neighbors(width, height, Topology::CrossSurface);
+        assert!(cross.contains(&Index { row: 3, col: 4 }));
+        assert!(cross.contains(&Index { row: 2, col: 4 }));
+        assert!(cross.contains(&Index { row: 1, col: 4 }));
+        assert!(!cross.contains(&Index { row: 0, col: 4 }));
+    }
 }
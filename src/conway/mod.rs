@@ -4,92 +4,196 @@
 //! * counting the cells of an organism, and
 //! * determining whether an organism is dying.
 
+mod bitboard;
 mod cell;
+mod delta;
 mod error;
 mod grid;
+mod hashlife;
 mod index;
-
-pub use cell::{Cell, CELL_SIZE};
+mod organism;
+mod pattern;
+mod rule;
+mod soup;
+mod stability;
+mod timeline;
+mod topology;
+mod universe;
+
+pub use cell::CELL_SIZE;
+pub use delta::GenerationDelta;
 pub use error::GameError;
 pub use grid::SubgridValuesIter;
-pub use grid::{Grid, GRID_HEIGHT, GRID_WIDTH, NUMBER_OF_SUBGRIDS};
+pub use grid::{Engine, Grid, NUMBER_OF_SUBGRIDS};
+pub use hashlife::HashLife;
 pub use index::Index;
+pub use organism::OrganismTracker;
+pub use pattern::{library, Pattern, PatternFormat};
+#[cfg(test)]
+pub use rule::Rule;
+pub use soup::Symmetry;
+#[cfg(test)]
+pub use soup::DEFAULT_DENSITY;
+#[cfg(test)]
+pub use stability::Stability;
+pub use stability::{Policy, StabilityPolicy};
+pub use topology::Topology;
 
 pub type Result<V> = std::result::Result<V, GameError>;
 
+/// Identifies a grid within a `Conway` game. Ids are never reused, so an id of a removed grid
+/// doesn't suddenly point at another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GridId(usize);
+
+impl std::fmt::Display for GridId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 /// A structure holding all the grids, that are being played at the same time
 #[derive(Clone)]
 pub struct Conway {
     pub stopped: bool,
-    grids: Vec<Grid>,
+    /// Grids in the order they were added in
+    grids: Vec<(GridId, Grid)>,
+    next_id: usize,
     last_gen_index: usize,
+    /// What happens to the grids that stopped evolving
+    policy: StabilityPolicy,
 }
 
 impl Conway {
     pub fn new() -> Self {
         Self {
             grids: Vec::new(),
+            next_id: 0,
             stopped: false,
             last_gen_index: 0,
+            policy: StabilityPolicy::default(),
         }
     }
 
     /// Creates new Conway game with `capacity` number of games. All of the games are being
     /// randomized and started
     pub fn start_with_capacity(capacity: usize) -> Self {
-        let mut grids = Vec::<Grid>::with_capacity(capacity);
+        let mut conway = Self::new();
 
         for _ in 0..capacity {
-            grids.push(Grid::random());
+            let mut grid = Grid::random();
+            grid.start();
+            conway.add_game(grid);
         }
+        conway.stopped = true;
 
-        Self {
-            grids,
-            stopped: true,
-            last_gen_index: 0,
-        }
+        conway
     }
 
-    /// Returns iterator over the games
-    pub fn iter(&self) -> std::slice::Iter<Grid> {
-        self.grids.iter()
+    /// Returns iterator over the games and their ids, in the order they were added in
+    pub fn iter(&self) -> impl Iterator<Item = (GridId, &Grid)> {
+        self.grids.iter().map(|(id, grid)| (*id, grid))
     }
 
-    /// Returns mutable iterator over the games
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<Grid> {
-        self.grids.iter_mut()
+    /// Returns mutable iterator over the games and their ids
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (GridId, &mut Grid)> {
+        self.grids.iter_mut().map(|(id, grid)| (*id, grid))
     }
 
-    /// Adds a new grid to the game
-    pub fn add_game(&mut self, game: Grid) {
-        self.grids.push(game);
+    pub fn get(&self, id: GridId) -> Option<&Grid> {
+        self.grids
+            .iter()
+            .find(|(grid_id, _)| *grid_id == id)
+            .map(|(_, grid)| grid)
+    }
+
+    pub fn get_mut(&mut self, id: GridId) -> Option<&mut Grid> {
+        self.grids
+            .iter_mut()
+            .find(|(grid_id, _)| *grid_id == id)
+            .map(|(_, grid)| grid)
+    }
+
+    /// Like `get_mut`, but fails for unknown ids
+    fn grid_mut(&mut self, id: GridId) -> Result<&mut Grid> {
+        self.get_mut(id).ok_or(GameError::UnknownGrid(id))
+    }
+
+    pub fn stability_policy(&self) -> StabilityPolicy {
+        self.policy
+    }
+
+    /// Sets what happens to the grids that became empty, static or periodic. Grids are left
+    /// alone by default.
+    pub fn set_stability_policy(&mut self, policy: StabilityPolicy) {
+        self.policy = policy;
+    }
+
+    /// Adds a new grid to the game and returns its id
+    pub fn add_game(&mut self, game: Grid) -> GridId {
+        let id = GridId(self.next_id);
+        self.next_id += 1;
+        self.grids.push((id, game));
+        id
     }
 
     pub fn number_of_games(&self) -> usize {
         self.grids.len()
     }
 
-    /// Removes the grid with the `index`
-    // NOTE: It might be a good idea to use some identifiers for the games
-    pub fn remove_game(&mut self, index: usize) -> Result<Grid> {
-        if index >= self.number_of_games() {
-            return Err(GameError::GameIndexOutOfBounds(index));
+    /// Removes the grid with the `id`
+    pub fn remove_game(&mut self, id: GridId) -> Result<Grid> {
+        let position = self
+            .grids
+            .iter()
+            .position(|(grid_id, _)| *grid_id == id)
+            .ok_or(GameError::UnknownGrid(id))?;
+
+        // keep the round robin of `gen_one` pointing at the same grid
+        if position < self.last_gen_index {
+            self.last_gen_index -= 1;
+        }
+        let (_, grid) = self.grids.remove(position);
+        if self.last_gen_index >= self.grids.len() {
+            self.last_gen_index = 0;
         }
 
-        Ok(self.grids.remove(index))
+        Ok(grid)
+    }
+
+    /// Computes the next generation of all the grids that aren't stopped. Returns the cells that
+    /// were born and died in each of them, except for the grids rewritten by the stability policy.
+    pub fn next_gen(&mut self) -> Vec<(GridId, GenerationDelta)> {
+        self.next_gen_where(|_| true)
     }
 
-    pub fn next_gen(&mut self) {
+    /// Like `next_gen`, but only for the grids for which `due` returns true
+    pub fn next_gen_where(
+        &mut self,
+        mut due: impl FnMut(GridId) -> bool,
+    ) -> Vec<(GridId, GenerationDelta)> {
         if self.stopped {
-            return;
+            return Vec::new();
         }
 
-        self.grids.iter_mut().for_each(|grid| {
-            grid.next_gen();
-        })
+        let policy = self.policy;
+        self.grids
+            .iter_mut()
+            .filter(|(id, _)| due(*id))
+            .filter_map(|(id, grid)| {
+                let delta = grid.next_gen_with_delta()?;
+                if grid.apply_policy(&policy) {
+                    None
+                } else {
+                    Some((*id, delta))
+                }
+            })
+            .collect()
     }
 
-    pub fn gen_one(&mut self) -> Option<usize> {
+    /// Computes the next generation of a single grid, taking turns between the grids. Returns the
+    /// id of the grid, or `None` if all the grids are stopped.
+    pub fn gen_one(&mut self) -> Option<GridId> {
         if self.stopped || self.grids.is_empty() {
             return None;
         }
@@ -97,12 +201,12 @@ impl Conway {
 
         // make sure to run one, unless all grids are stopped, prevent infinite loop
         for _ in 0..number_of_grids {
-            if self.grids[self.last_gen_index].next_gen() {
-                let idx = self.last_gen_index;
-                self.last_gen_index = (self.last_gen_index + 1) % number_of_grids;
-                return Some(idx);
-            }
+            let (id, grid) = &mut self.grids[self.last_gen_index];
             self.last_gen_index = (self.last_gen_index + 1) % number_of_grids;
+            if grid.next_gen() {
+                grid.apply_policy(&self.policy);
+                return Some(*id);
+            }
         }
 
         None
@@ -120,27 +224,18 @@ impl Conway {
         self.stopped = !self.stopped;
     }
 
-    pub fn stop_game(&mut self, game_index: usize) -> Result<()> {
-        self.grids
-            .get_mut(game_index)
-            .ok_or_else(|| GameError::GameIndexOutOfBounds(game_index))?
-            .stop();
+    pub fn stop_game(&mut self, id: GridId) -> Result<()> {
+        self.grid_mut(id)?.stop();
         Ok(())
     }
 
-    pub fn start_game(&mut self, game_index: usize) -> Result<()> {
-        self.grids
-            .get_mut(game_index)
-            .ok_or_else(|| GameError::GameIndexOutOfBounds(game_index))?
-            .start();
+    pub fn start_game(&mut self, id: GridId) -> Result<()> {
+        self.grid_mut(id)?.start();
         Ok(())
     }
 
-    pub fn toggle_game(&mut self, game_index: usize) -> Result<()> {
-        self.grids
-            .get_mut(game_index)
-            .ok_or_else(|| GameError::GameIndexOutOfBounds(game_index))?
-            .toggle();
+    pub fn toggle_game(&mut self, id: GridId) -> Result<()> {
+        self.grid_mut(id)?.toggle();
         Ok(())
     }
 
@@ -151,21 +246,23 @@ impl Conway {
     pub fn get_pitch_and_volume_per_subgrids(&mut self) -> Vec<SubgridValuesIter> {
         self.grids
             .iter_mut()
-            .map(Grid::get_pitch_and_volume_per_subgrid)
+            .map(|(_, grid)| grid.get_pitch_and_volume_per_subgrid())
             .collect()
     }
 
     /// Returns (pitch, volume) for choosen game
-    pub fn get_pitch_and_volume_for_grid(&self, game_idx: usize) -> Result<(u32, u32)> {
+    pub fn get_pitch_and_volume_for_grid(&self, id: GridId) -> Result<(u32, u32)> {
         Ok(self
-            .grids
-            .get(game_idx)
-            .ok_or_else(|| GameError::IndexOutOfBounds(game_idx.into()))?
+            .get(id)
+            .ok_or(GameError::UnknownGrid(id))?
             .get_pitch_and_volume())
     }
 
     pub fn get_pitch_and_volume_for_grids(&self) -> Vec<(u32, u32)> {
-        self.grids.iter().map(Grid::get_pitch_and_volume).collect()
+        self.grids
+            .iter()
+            .map(|(_, grid)| grid.get_pitch_and_volume())
+            .collect()
     }
 }
 
@@ -178,25 +275,146 @@ mod test {
         let mut games = Conway::start_with_capacity(5);
         assert_eq!(games.number_of_games(), 5);
 
-        games.add_game(Grid::random());
+        let first = games.add_game(Grid::random());
         assert_eq!(games.number_of_games(), 6);
 
-        games.add_game(Grid::random());
+        let second = games.add_game(Grid::random());
         assert_eq!(games.number_of_games(), 7);
 
-        games.remove_game(1).unwrap();
+        games.remove_game(first).unwrap();
         assert_eq!(games.number_of_games(), 6);
 
-        games.remove_game(1).unwrap();
+        games.remove_game(second).unwrap();
         assert_eq!(games.number_of_games(), 5);
     }
 
+    #[test]
+    fn stable_ids() {
+        let mut games = Conway::new();
+        let ids: Vec<GridId> = (0..3).map(|_| games.add_game(Grid::empty())).collect();
+        games.remove_game(ids[0]).unwrap();
+
+        // the other grids keep their ids
+        games.toggle_game(ids[2]).unwrap();
+        assert!(!games.get(ids[2]).unwrap().stopped);
+        assert!(games.get(ids[1]).unwrap().stopped);
+
+        // ids of removed grids aren't reused
+        let new = games.add_game(Grid::empty());
+        assert!(!ids.contains(&new));
+        for result in &[
+            games.remove_game(ids[0]).map(|_| ()),
+            games.toggle_game(ids[0]),
+            games.get_pitch_and_volume_for_grid(ids[0]).map(|_| ()),
+        ] {
+            match result {
+                Err(GameError::UnknownGrid(id)) => assert_eq!(*id, ids[0]),
+                other => panic!("{:?}", other),
+            }
+        }
+        assert_eq!(
+            games.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            vec![ids[1], ids[2], new]
+        );
+    }
+
+    #[test]
+    fn gen_one_takes_turns() {
+        let mut games = Conway::new();
+        let ids: Vec<GridId> = (0..3)
+            .map(|seed| {
+                let mut grid = Grid::random_with(seed, DEFAULT_DENSITY);
+                grid.start();
+                games.add_game(grid)
+            })
+            .collect();
+        games.stop_game(ids[1]).unwrap();
+
+        assert_eq!(games.gen_one(), Some(ids[0]));
+        assert_eq!(games.gen_one(), Some(ids[2]));
+        games.remove_game(ids[0]).unwrap();
+        assert_eq!(games.gen_one(), Some(ids[2]));
+
+        games.stop_game(ids[2]).unwrap();
+        assert_eq!(games.gen_one(), None);
+    }
+
+    #[test]
+    fn stability_policy() {
+        let mut games = Conway::new();
+        games.set_stability_policy(StabilityPolicy {
+            still: Policy::Stop,
+            ..Default::default()
+        });
+        for name in &["Block", "Blinker"] {
+            let mut grid = Grid::with_pattern(name, Index::new(10, 10)).unwrap();
+            grid.start();
+            games.add_game(grid);
+        }
+
+        for _ in 0..4 {
+            games.next_gen();
+        }
+        let stopped: Vec<bool> = games.iter().map(|(_, grid)| grid.stopped).collect();
+        assert_eq!(stopped, vec![true, false]);
+        assert_eq!(
+            games.iter().nth(1).unwrap().1.stability(),
+            Stability::Periodic(2)
+        );
+    }
+
+    #[test]
+    fn next_gen_deltas() {
+        let mut games = Conway::new();
+        games.set_stability_policy(StabilityPolicy {
+            periodic: Policy::Reseed,
+            ..Default::default()
+        });
+        let ids: Vec<GridId> = ["Blinker", "Block"]
+            .iter()
+            .map(|name| games.add_game(Grid::with_pattern(name, Index::new(10, 10)).unwrap()))
+            .collect();
+        games.start_game(ids[0]).unwrap();
+
+        let deltas = games.next_gen();
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].0, ids[0]);
+        assert_eq!(deltas[0].1.born.len(), 2);
+        assert_eq!(deltas[0].1.died.len(), 2);
+
+        // the blinker repeats itself and gets reseeded, so its delta would be incomplete
+        assert!(games.next_gen().is_empty());
+        assert!(games.get(ids[0]).unwrap().soup().is_some());
+    }
+
+    #[test]
+    fn next_gen_where() {
+        let mut games = Conway::new();
+        let ids: Vec<GridId> = (0..2)
+            .map(|_| {
+                let mut grid = Grid::with_pattern("Glider", Index::new(10, 10)).unwrap();
+                grid.start();
+                games.add_game(grid)
+            })
+            .collect();
+
+        let deltas = games.next_gen_where(|id| id == ids[1]);
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].0, ids[1]);
+        assert_eq!(games.get(ids[0]).unwrap().generation(), 0);
+        assert_eq!(games.get(ids[1]).unwrap().generation(), 1);
+    }
+
     use std::time::Instant;
 
     #[test]
     fn test_if_grids_change() {
-        let mut games = Conway::start_with_capacity(10);
-        games.start();
+        let mut games = Conway::new();
+        for seed in 0..10 {
+            let mut grid = Grid::random_with(seed, DEFAULT_DENSITY);
+            grid.start();
+            games.add_game(grid);
+        }
 
         let old_games = games.clone();
         let now = Instant::now();
@@ -206,46 +424,45 @@ mod test {
         assert!(!games
             .iter()
             .zip(old_games.iter())
-            .any(|(new, old)| old == new));
+            .any(|((_, new), (_, old))| old == new));
 
         let old_games = games.clone();
         games.next_gen();
         assert!(!games
             .iter()
             .zip(old_games.iter())
-            .any(|(new, old)| old == new));
+            .any(|((_, new), (_, old))| old == new));
 
         let old_games = games.clone();
         games.next_gen();
         assert!(!games
             .iter()
             .zip(old_games.iter())
-            .any(|(new, old)| old == new));
+            .any(|((_, new), (_, old))| old == new));
     }
 
     #[test]
     fn test_pitch_and_volume() {
-        let mut games = Conway::start_with_capacity(10);
-        games.start();
+        let mut games = Conway::new();
+        for seed in 0..10 {
+            let mut grid = Grid::random_with(seed, DEFAULT_DENSITY);
+            grid.start();
+            games.add_game(grid);
+        }
 
         games.next_gen();
-        let number_of_games = games.grids.len();
-        let mut pitches_and_volumes = games.get_pitch_and_volume_per_subgrids();
-        let mut finished_grid_counter: usize = 0;
-
-        for i in 0..number_of_games * NUMBER_OF_SUBGRIDS {
-            let upper_bound: usize = (i + 1).min(number_of_games);
-            for grid_idx in finished_grid_counter..upper_bound {
-                match pitches_and_volumes[grid_idx].next() {
-                    Some((pitch, volume)) => {
-                        println!("({},{}), grid: {}", pitch, volume, grid_idx);
-                    }
-                    None => {
-                        finished_grid_counter += 1;
-                    }
-                }
-            }
-        }
-        assert!(false);
+        let per_grid = games.get_pitch_and_volume_for_grids();
+        let per_subgrid: Vec<(u32, u32)> = games
+            .get_pitch_and_volume_per_subgrids()
+            .into_iter()
+            .map(|values| {
+                assert_eq!(values.len(), NUMBER_OF_SUBGRIDS);
+                values.fold((0, 0), |(pitch, volume), &(p, v)| (pitch + p, volume + v))
+            })
+            .collect();
+
+        // the subgrids cover the whole grids
+        assert_eq!(per_subgrid, per_grid);
+        assert!(per_grid.iter().all(|&(pitch, volume)| pitch + volume > 0));
     }
 }
@@ -0,0 +1,332 @@
+//! Organisms are groups of alive cells touching each other, including diagonally. The edges of
+//! the grid are crossed according to its topology.
+
+use std::collections::{BTreeSet, HashMap};
+
+use super::grid::Grid;
+use super::index::Index;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Organism {
+    /// Cells of the organism, sorted by their offset in the grid
+    cells: Vec<Index>,
+}
+
+impl Organism {
+    pub fn cells(&self) -> &[Index] {
+        &self.cells
+    }
+
+    /// Number of the cells of the organism
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns the top left and bottom right corners (both inclusive) of the smallest rectangle
+    /// containing the organism. Organisms wrapping around the edges of the grid span the grid.
+    pub fn bounding_box(&self) -> (Index, Index) {
+        let first = self.cells[0];
+        self.cells.iter().fold((first, first), |(min, max), index| {
+            (
+                Index::new(min.row.min(index.row), min.col.min(index.col)),
+                Index::new(max.row.max(index.row), max.col.max(index.col)),
+            )
+        })
+    }
+
+    /// Average row and column of the cells of the organism
+    pub fn centroid(&self) -> (f64, f64) {
+        let (rows, cols) = self.cells.iter().fold((0, 0), |(rows, cols), index| {
+            (rows + index.row, cols + index.col)
+        });
+        let size = self.size() as f64;
+        (rows as f64 / size, cols as f64 / size)
+    }
+
+    /// Organism is dying, when more than half of its cells die in the next generation
+    pub fn is_dying(&self, grid: &Grid) -> bool {
+        let deaths = self
+            .cells
+            .iter()
+            .filter(|&&index| !grid.rule().survives(grid.count_neighbors(index)))
+            .count();
+        deaths * 2 > self.size()
+    }
+}
+
+/// Returns the organisms of the current generation of the grid, ordered by their first cell
+pub fn organisms(grid: &Grid) -> Vec<Organism> {
+    let (width, height) = (grid.width(), grid.height());
+    let mut visited = vec![false; width * height];
+    let mut organisms = Vec::new();
+
+    for (idx, cell) in grid.iter().enumerate() {
+        if !cell.alive || visited[idx] {
+            continue;
+        }
+
+        visited[idx] = true;
+        let mut cells = vec![Index::from_offset(idx, width)];
+        let mut next = 0;
+        while next < cells.len() {
+            for neighbor in cells[next].neighbors(width, height, grid.topology()) {
+                let offset = neighbor.to_offset(width);
+                if !visited[offset] && grid.get_cell(offset).map_or(false, |cell| cell.alive) {
+                    visited[offset] = true;
+                    cells.push(neighbor);
+                }
+            }
+            next += 1;
+        }
+
+        cells.sort_by_key(|index| index.to_offset(width));
+        organisms.push(Organism { cells });
+    }
+
+    organisms
+}
+
+/// Identifies an organism across generations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OrganismId(pub usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrganismEvent {
+    /// Organism appeared where there was no organism before
+    Birth(OrganismId),
+    /// Organism disappeared without leaving any descendants
+    Death(OrganismId),
+    /// Several organisms grew into a single new one
+    Merge {
+        from: Vec<OrganismId>,
+        into: OrganismId,
+    },
+    /// Organism fell apart into several new ones
+    Split {
+        from: OrganismId,
+        into: Vec<OrganismId>,
+    },
+}
+
+/// Follows the organisms of a grid from one generation to another. An organism of the new
+/// generation descends from the organisms of the previous generation that were touching its
+/// cells. An organism with a single ancestor, which has no other descendants, keeps its id.
+#[derive(Debug, Clone, Default)]
+pub struct OrganismTracker {
+    organisms: Vec<(OrganismId, Organism)>,
+    next_id: usize,
+}
+
+impl OrganismTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Organisms found by the last call to `track`
+    pub fn organisms(&self) -> &[(OrganismId, Organism)] {
+        &self.organisms
+    }
+
+    /// Finds the organisms of the current generation of the grid and reports how they came to be
+    /// from the organisms found the last time. The first call reports births only.
+    pub fn track(&mut self, grid: &Grid) -> Vec<OrganismEvent> {
+        let (width, height) = (grid.width(), grid.height());
+
+        // cells the previous organisms could have influenced
+        let mut zones: HashMap<usize, BTreeSet<usize>> = HashMap::new();
+        for (old, (_, organism)) in self.organisms.iter().enumerate() {
+            for &index in organism.cells() {
+                let zone = index.neighbors(width, height, grid.topology());
+                for cell in zone.into_iter().chain(Some(index)) {
+                    zones.entry(cell.to_offset(width)).or_default().insert(old);
+                }
+            }
+        }
+
+        let current = organisms(grid);
+        let ancestors: Vec<BTreeSet<usize>> = current
+            .iter()
+            .map(|organism| {
+                organism
+                    .cells()
+                    .iter()
+                    .filter_map(|index| zones.get(&index.to_offset(width)))
+                    .flatten()
+                    .copied()
+                    .collect()
+            })
+            .collect();
+        let mut descendants = vec![Vec::new(); self.organisms.len()];
+        for (new, ancestors) in ancestors.iter().enumerate() {
+            for &old in ancestors {
+                descendants[old].push(new);
+            }
+        }
+
+        let mut events = Vec::new();
+        let mut ids = Vec::with_capacity(current.len());
+        for ancestors in &ancestors {
+            let id = match ancestors.iter().next() {
+                Some(&old) if ancestors.len() == 1 && descendants[old].len() == 1 => {
+                    self.organisms[old].0
+                }
+                _ => self.new_id(),
+            };
+            match ancestors.len() {
+                0 => events.push(OrganismEvent::Birth(id)),
+                1 => {}
+                _ => events.push(OrganismEvent::Merge {
+                    from: ancestors.iter().map(|&old| self.organisms[old].0).collect(),
+                    into: id,
+                }),
+            }
+            ids.push(id);
+        }
+
+        for (old, descendants) in descendants.iter().enumerate() {
+            let from = self.organisms[old].0;
+            match descendants.len() {
+                0 => events.push(OrganismEvent::Death(from)),
+                1 => {}
+                _ => events.push(OrganismEvent::Split {
+                    from,
+                    into: descendants.iter().map(|&new| ids[new]).collect(),
+                }),
+            }
+        }
+
+        self.organisms = ids.into_iter().zip(current).collect();
+        events
+    }
+
+    fn new_id(&mut self) -> OrganismId {
+        let id = OrganismId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conway::Topology;
+
+    fn grid_with(cells: &[(usize, usize)], width: usize, height: usize) -> Grid {
+        let mut grid = Grid::empty_with_size(width, height);
+        for &(row, col) in cells {
+            grid.set_cell(Index::new(row, col).to_offset(width), true)
+                .unwrap();
+        }
+        grid.start();
+        grid
+    }
+
+    #[test]
+    fn labelling() {
+        // a diagonal pair, a single cell and a block
+        let grid = grid_with(
+            &[(0, 0), (1, 1), (0, 5), (5, 5), (5, 6), (6, 5), (6, 6)],
+            10,
+            10,
+        );
+        let found = organisms(&grid);
+        let sizes: Vec<usize> = found.iter().map(Organism::size).collect();
+        assert_eq!(sizes, vec![2, 1, 4]);
+
+        let block = &found[2];
+        assert_eq!(block.bounding_box(), (Index::new(5, 5), Index::new(6, 6)));
+        assert_eq!(block.centroid(), (5.5, 5.5));
+        assert_eq!(organisms(&Grid::empty()), vec![]);
+    }
+
+    #[test]
+    fn labelling_across_edges() {
+        let mut grid = grid_with(&[(0, 0), (9, 9), (4, 9), (4, 0)], 10, 10);
+        assert_eq!(organisms(&grid).len(), 4);
+
+        grid.set_topology(Topology::Torus);
+        assert_eq!(organisms(&grid).len(), 2);
+        assert_eq!(
+            organisms(&grid)[0].bounding_box(),
+            (Index::new(0, 0), Index::new(9, 9))
+        );
+    }
+
+    #[test]
+    fn dying() {
+        let grid = grid_with(&[(2, 2), (5, 5), (5, 6), (6, 5), (6, 6)], 10, 10);
+        let found = organisms(&grid);
+        assert!(found[0].is_dying(&grid));
+        assert!(!found[1].is_dying(&grid));
+    }
+
+    #[test]
+    fn tracking() {
+        let mut tracker = OrganismTracker::new();
+        let mut grid = Grid::with_pattern("Glider", Index::new(5, 5)).unwrap();
+        grid.start();
+
+        assert_eq!(
+            tracker.track(&grid),
+            vec![OrganismEvent::Birth(OrganismId(0))]
+        );
+        // the glider keeps its identity while it moves
+        for _ in 0..8 {
+            grid.next_gen();
+            assert_eq!(tracker.track(&grid), vec![]);
+        }
+        assert_eq!(tracker.organisms()[0].0, OrganismId(0));
+        assert_eq!(tracker.organisms()[0].1.size(), 5);
+    }
+
+    #[test]
+    fn births_and_deaths() {
+        let mut tracker = OrganismTracker::new();
+        let mut grid = grid_with(&[(2, 2), (7, 7), (7, 8), (8, 7), (8, 8)], 10, 10);
+        tracker.track(&grid);
+
+        grid.next_gen();
+        grid.set_cell(Index::new(0, 9).to_offset(10), true).unwrap();
+        assert_eq!(
+            tracker.track(&grid),
+            vec![
+                OrganismEvent::Birth(OrganismId(2)),
+                OrganismEvent::Death(OrganismId(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_and_splits() {
+        let mut tracker = OrganismTracker::new();
+        // two cells far apart
+        let mut grid = grid_with(&[(2, 2), (2, 5)], 10, 10);
+        tracker.track(&grid);
+
+        // a row bridging them
+        for col in 2..6 {
+            grid.set_cell(Index::new(2, col).to_offset(10), true)
+                .unwrap();
+        }
+        assert_eq!(
+            tracker.track(&grid),
+            vec![OrganismEvent::Merge {
+                from: vec![OrganismId(0), OrganismId(1)],
+                into: OrganismId(2)
+            }]
+        );
+
+        // the middle of the row disappears
+        for col in 3..5 {
+            grid.set_cell(Index::new(2, col).to_offset(10), false)
+                .unwrap();
+        }
+        assert_eq!(
+            tracker.track(&grid),
+            vec![OrganismEvent::Split {
+                from: OrganismId(2),
+                into: vec![OrganismId(3), OrganismId(4)]
+            }]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Catalogue of well known patterns, which can be stamped onto a grid by their names.
+
+use super::{rle, Pattern};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    Gun,
+}
+
+#[derive(Debug)]
+pub struct PatternInfo {
+    pub name: &'static str,
+    pub category: Category,
+    /// Number of generations after which the pattern repeats itself, 1 for still lifes
+    pub period: usize,
+    /// Rows and columns the pattern moves by during a single period
+    pub displacement: (isize, isize),
+    rle: &'static str,
+}
+
+impl PatternInfo {
+    pub fn pattern(&self) -> Pattern {
+        let mut pattern = rle::parse(self.rle).expect("patterns in the library are valid");
+        pattern.name = Some(self.name.to_string());
+        pattern
+    }
+}
+
+/// Returns the pattern with the `name`, ignoring the case of the letters
+pub fn find(name: &str) -> Option<&'static PatternInfo> {
+    PATTERNS
+        .iter()
+        .find(|info| info.name.eq_ignore_ascii_case(name))
+}
+
+pub const PATTERNS: &[PatternInfo] = &[
+    PatternInfo {
+        name: "Block",
+        category: Category::StillLife,
+        period: 1,
+        displacement: (0, 0),
+        rle: "x = 2, y = 2\n2o$2o!",
+    },
+    PatternInfo {
+        name: "Beehive",
+        category: Category::StillLife,
+        period: 1,
+        displacement: (0, 0),
+        rle: "x = 4, y = 3\nb2o$o2bo$b2o!",
+    },
+    PatternInfo {
+        name: "Loaf",
+        category: Category::StillLife,
+        period: 1,
+        displacement: (0, 0),
+        rle: "x = 4, y = 4\nb2o$o2bo$bobo$2bo!",
+    },
+    PatternInfo {
+        name: "Boat",
+        category: Category::StillLife,
+        period: 1,
+        displacement: (0, 0),
+        rle: "x = 3, y = 3\n2o$obo$bo!",
+    },
+    PatternInfo {
+        name: "Tub",
+        category: Category::StillLife,
+        period: 1,
+        displacement: (0, 0),
+        rle: "x = 3, y = 3\nbo$obo$bo!",
+    },
+    PatternInfo {
+        name: "Blinker",
+        category: Category::Oscillator,
+        period: 2,
+        displacement: (0, 0),
+        rle: "x = 3, y = 1\n3o!",
+    },
+    PatternInfo {
+        name: "Toad",
+        category: Category::Oscillator,
+        period: 2,
+        displacement: (0, 0),
+        rle: "x = 4, y = 2\nb3o$3o!",
+    },
+    PatternInfo {
+        name: "Beacon",
+        category: Category::Oscillator,
+        period: 2,
+        displacement: (0, 0),
+        rle: "x = 4, y = 4\n2o$2o$2b2o$2b2o!",
+    },
+    PatternInfo {
+        name: "Pulsar",
+        category: Category::Oscillator,
+        period: 3,
+        displacement: (0, 0),
+        rle: "x = 13, y = 13\n2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo\
+              $o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    },
+    PatternInfo {
+        name: "Pentadecathlon",
+        category: Category::Oscillator,
+        period: 15,
+        displacement: (0, 0),
+        rle: "x = 10, y = 3\n2bo4bo$2ob4ob2o$2bo4bo!",
+    },
+    PatternInfo {
+        name: "Glider",
+        category: Category::Spaceship,
+        period: 4,
+        displacement: (1, 1),
+        rle: "x = 3, y = 3\nbo$2bo$3o!",
+    },
+    PatternInfo {
+        name: "LWSS",
+        category: Category::Spaceship,
+        period: 4,
+        displacement: (0, -2),
+        rle: "x = 5, y = 4\nbo2bo$o$o3bo$4o!",
+    },
+    PatternInfo {
+        name: "MWSS",
+        category: Category::Spaceship,
+        period: 4,
+        displacement: (0, -2),
+        rle: "x = 6, y = 5\n3bo$bo3bo$o$o4bo$5o!",
+    },
+    PatternInfo {
+        name: "HWSS",
+        category: Category::Spaceship,
+        period: 4,
+        displacement: (0, -2),
+        rle: "x = 7, y = 5\n3b2o$bo4bo$o$o5bo$6o!",
+    },
+    PatternInfo {
+        name: "Gosper glider gun",
+        category: Category::Gun,
+        period: 30,
+        displacement: (0, 0),
+        rle:
+            "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b\
+              obo$10bo5bo7bo$11bo3bo$12b2o!",
+    },
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conway::{GameError, Grid, Index};
+
+    const SIZE: usize = 60;
+
+    fn alive_cells(grid: &Grid) -> Vec<Index> {
+        grid.iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.alive)
+            .map(|(idx, _)| Index::from_offset(idx, SIZE))
+            .collect()
+    }
+
+    #[test]
+    fn all_patterns_are_valid() {
+        for info in PATTERNS {
+            let pattern = info.pattern();
+            assert!(!pattern.cells.is_empty(), "{}", info.name);
+            assert_eq!(pattern.name.as_deref(), Some(info.name));
+        }
+    }
+
+    #[test]
+    fn periods_and_displacements() {
+        for info in PATTERNS {
+            let mut grid = Grid::empty_with_size(SIZE, SIZE);
+            grid.load_pattern(&info.pattern(), Index::new(10, 10))
+                .unwrap();
+            grid.start();
+            let initial = alive_cells(&grid);
+
+            for generation in 1..=info.period {
+                grid.next_gen();
+                let moved: Vec<Index> = initial
+                    .iter()
+                    .map(|index| {
+                        Index::new(
+                            (index.row as isize + info.displacement.0) as usize,
+                            (index.col as isize + info.displacement.1) as usize,
+                        )
+                    })
+                    .collect();
+
+                let current = alive_cells(&grid);
+                if info.category == Category::Gun {
+                    // guns keep their shape, but they also shoot out gliders
+                    if generation == info.period {
+                        assert!(moved.iter().all(|index| current.contains(index)));
+                        assert!(current.len() > moved.len(), "{}", info.name);
+                    }
+                } else {
+                    assert_eq!(
+                        current == moved,
+                        generation == info.period,
+                        "{} in generation {}",
+                        info.name,
+                        generation
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_by_name() {
+        assert_eq!(find("glider").unwrap().name, "Glider");
+        assert_eq!(find("GOSPER GLIDER GUN").unwrap().period, 30);
+        assert!(find("unicorn").is_none());
+    }
+
+    #[test]
+    fn grid_with_pattern() {
+        let grid = Grid::with_pattern("Pulsar", Index::new(10, 10)).unwrap();
+        assert_eq!(grid.count_ones(), 48);
+
+        match Grid::with_pattern("unicorn", Index::new(0, 0)) {
+            Err(GameError::UnknownPattern(name)) => assert_eq!(name, "unicorn"),
+            _ => panic!("unknown pattern shouldn't be found"),
+        }
+        assert!(Grid::with_pattern("Pulsar", Index::new(45, 45)).is_err());
+    }
+}
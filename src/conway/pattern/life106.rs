@@ -0,0 +1,198 @@
+//! Life 1.06 patterns, which list the coordinates of the alive cells:
+//!
+//! ```text
+//! #Life 1.06
+//! 1 0
+//! 2 1
+//! 0 2
+//! 1 2
+//! 2 2
+//! ```
+//!
+//! Each line holds the column and the row of a single cell. The coordinates can be negative,
+//! the pattern is moved so its top left corner lies at (0, 0).
+
+use super::{invalid, Pattern};
+use crate::conway::{Grid, Index, Result};
+use std::convert::TryFrom;
+
+const HEADER: &str = "#Life 1.06";
+
+pub fn parse(input: &str) -> Result<Pattern> {
+    let mut pattern = Pattern::default();
+    let mut lines = input.lines().enumerate();
+
+    match lines.next() {
+        Some((_, line)) if line.trim() == HEADER => {}
+        _ => return Err(invalid(1, format!("expected '{}' header", HEADER))),
+    }
+
+    let mut coordinates: Vec<(i64, i64)> = Vec::new();
+    for (line_idx, line) in lines {
+        let line_number = line_idx + 1;
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut chars = comment.chars();
+            let kind = chars.next();
+            let text = chars.as_str().trim().to_string();
+            match kind {
+                Some('N') => pattern.name = Some(text),
+                Some('D') | Some('C') => pattern.comments.push(text),
+                _ => {}
+            }
+            continue;
+        }
+
+        let mut numbers = line.split_whitespace().map(|number| number.parse::<i64>());
+        match (numbers.next(), numbers.next(), numbers.next()) {
+            (Some(Ok(col)), Some(Ok(row)), None) => coordinates.push((row, col)),
+            _ => {
+                return Err(invalid(
+                    line_number,
+                    format!("expected two coordinates, got '{}'", line),
+                ))
+            }
+        }
+    }
+
+    coordinates.sort_unstable();
+    coordinates.dedup();
+
+    if let (Some(min_row), Some(min_col), Some(max_row), Some(max_col)) = (
+        coordinates.iter().map(|&(row, _)| row).min(),
+        coordinates.iter().map(|&(_, col)| col).min(),
+        coordinates.iter().map(|&(row, _)| row).max(),
+        coordinates.iter().map(|&(_, col)| col).max(),
+    ) {
+        let size = |min: i64, max: i64| {
+            max.checked_sub(min)
+                .and_then(|size| size.checked_add(1))
+                .and_then(|size| usize::try_from(size).ok())
+                .ok_or_else(|| invalid(0, "coordinates are too far apart"))
+        };
+        pattern.width = size(min_col, max_col)?;
+        pattern.height = size(min_row, max_row)?;
+        pattern.cells = coordinates
+            .iter()
+            .map(|&(row, col)| Index::new((row - min_row) as usize, (col - min_col) as usize))
+            .collect();
+    }
+
+    Ok(pattern)
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut output = format!("{}\n", HEADER);
+
+    if let Some(name) = &pattern.name {
+        output.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        output.push_str(&format!("#D {}\n", comment));
+    }
+    for index in &pattern.cells {
+        output.push_str(&format!("{} {}\n", index.col, index.row));
+    }
+
+    output
+}
+
+/// Exports the live region of the grid
+pub fn write_grid(grid: &Grid) -> String {
+    write(&Pattern::from_grid(grid))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::assert_grid_round_trip;
+    use super::*;
+    use crate::conway::GameError;
+
+    const GLIDER: &str = "#Life 1.06
+#N Glider
+1 0
+2 1
+0 2
+1 2
+2 2
+";
+
+    fn glider_cells() -> Vec<Index> {
+        vec![
+            Index::new(0, 1),
+            Index::new(1, 2),
+            Index::new(2, 0),
+            Index::new(2, 1),
+            Index::new(2, 2),
+        ]
+    }
+
+    #[test]
+    fn parse_glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, glider_cells());
+    }
+
+    #[test]
+    fn negative_and_unordered_coordinates() {
+        let pattern = parse("#Life 1.06\n1 1\n-1 -2\n0 -1\n-1 1\n0 1\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 4));
+        assert_eq!(
+            pattern.cells,
+            vec![
+                Index::new(0, 0),
+                Index::new(1, 1),
+                Index::new(3, 0),
+                Index::new(3, 1),
+                Index::new(3, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(write(&pattern), GLIDER);
+        assert_eq!(parse(&write(&pattern)).unwrap(), pattern);
+    }
+
+    #[test]
+    fn grid_round_trip() {
+        assert_grid_round_trip(parse, write_grid);
+    }
+
+    #[test]
+    fn distant_coordinates() {
+        let input = format!("#Life 1.06\n0 {}\n0 {}\n", i64::MIN, i64::MAX);
+        match parse(&input) {
+            Err(GameError::InvalidPattern { line, .. }) => assert_eq!(line, 0),
+            other => panic!("{:?}", other),
+        }
+        let pattern = parse(&format!("#Life 1.06\n{0} {0}\n", i64::MIN)).unwrap();
+        assert_eq!(pattern.cells, vec![Index::new(0, 0)]);
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        for &(input, expected_line) in &[
+            ("1 0\n2 1\n", 1),
+            ("#Life 1.05\n1 0\n", 1),
+            ("#Life 1.06\n1 0\n2\n", 3),
+            ("#Life 1.06\n1 0 3\n", 2),
+            ("#Life 1.06\n1 a\n", 2),
+        ] {
+            match parse(input) {
+                Err(GameError::InvalidPattern { line, .. }) => {
+                    assert_eq!(line, expected_line, "{:?}", input)
+                }
+                other => panic!("{:?} parsed as {:?}", input, other),
+            }
+        }
+    }
+}
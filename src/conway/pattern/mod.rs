@@ -0,0 +1,250 @@
+//! Patterns that can be stamped onto a grid, together with readers and writers of the file
+//! formats used to share them.
+
+pub mod library;
+pub mod life106;
+pub mod plaintext;
+pub mod rle;
+
+use super::grid::Grid;
+use super::index::Index;
+use super::rule::Rule;
+use super::GameError;
+use strum_macros::{Display, EnumIter, EnumString};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pattern {
+    pub name: Option<String>,
+    pub comments: Vec<String>,
+    /// Rule the pattern was designed for, if the file specifies it
+    pub rule: Option<Rule>,
+    pub width: usize,
+    pub height: usize,
+    /// Alive cells, relative to the top left corner of the pattern
+    pub cells: Vec<Index>,
+}
+
+impl Pattern {
+    /// Returns the smallest pattern containing all the alive cells of the grid
+    pub fn from_grid(grid: &Grid) -> Self {
+        let alive: Vec<Index> = grid
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.alive)
+            .map(|(idx, _)| Index::from_offset(idx, grid.width()))
+            .collect();
+
+        let min_row = alive.iter().map(|index| index.row).min().unwrap_or(0);
+        let min_col = alive.iter().map(|index| index.col).min().unwrap_or(0);
+        let max_row = alive.iter().map(|index| index.row + 1).max().unwrap_or(0);
+        let max_col = alive.iter().map(|index| index.col + 1).max().unwrap_or(0);
+
+        Self {
+            rule: Some(grid.rule()),
+            width: max_col - min_col,
+            height: max_row - min_row,
+            cells: alive
+                .iter()
+                .map(|index| Index::new(index.row - min_row, index.col - min_col))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// File formats the patterns are shared in
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, EnumString, Display)]
+pub enum PatternFormat {
+    #[strum(serialize = "RLE")]
+    Rle,
+    Plaintext,
+    #[strum(serialize = "Life 1.06")]
+    Life106,
+}
+
+impl PatternFormat {
+    /// Picks the format from the extension of the file, the unknown extensions are read as RLE
+    pub fn from_file_name(name: &str) -> Self {
+        let extension = name.rsplit('.').next().unwrap_or_default();
+        match extension.to_ascii_lowercase().as_str() {
+            "cells" => PatternFormat::Plaintext,
+            "lif" | "life" => PatternFormat::Life106,
+            _ => PatternFormat::Rle,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PatternFormat::Rle => "rle",
+            PatternFormat::Plaintext => "cells",
+            PatternFormat::Life106 => "lif",
+        }
+    }
+
+    pub fn parse(self, input: &str) -> super::Result<Pattern> {
+        match self {
+            PatternFormat::Rle => rle::parse(input),
+            PatternFormat::Plaintext => plaintext::parse(input),
+            PatternFormat::Life106 => life106::parse(input),
+        }
+    }
+
+    /// Exports the live region of the grid
+    pub fn write_grid(self, grid: &Grid) -> super::Result<String> {
+        match self {
+            PatternFormat::Rle => Ok(rle::write_grid(grid)),
+            PatternFormat::Plaintext => plaintext::write_grid(grid),
+            PatternFormat::Life106 => Ok(life106::write_grid(grid)),
+        }
+    }
+}
+
+/// Shorthand for the errors of the pattern readers
+fn invalid(line: usize, message: impl Into<String>) -> GameError {
+    GameError::InvalidPattern {
+        line,
+        message: message.into(),
+    }
+}
+
+/// Writes a grid with a few cells and checks that the read pattern recreates it
+#[cfg(test)]
+fn assert_grid_round_trip(
+    parse: impl Fn(&str) -> super::Result<Pattern>,
+    write_grid: impl Fn(&Grid) -> String,
+) {
+    let mut grid = Grid::empty_with_size(20, 20);
+    for &(row, col) in &[(3, 5), (3, 9), (6, 7), (9, 5), (9, 6)] {
+        grid.set_cell(Index::new(row, col).to_offset(20), true)
+            .unwrap();
+    }
+
+    let pattern = parse(&write_grid(&grid)).unwrap();
+    assert_eq!((pattern.width, pattern.height), (5, 7));
+
+    let mut loaded = Grid::empty_with_size(20, 20);
+    loaded.load_pattern(&pattern, Index::new(3, 5)).unwrap();
+    assert!(grid
+        .iter()
+        .zip(loaded.iter())
+        .all(|(g, l)| g.alive == l.alive));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    fn glider() -> Pattern {
+        Pattern {
+            width: 3,
+            height: 3,
+            cells: vec![
+                Index::new(0, 1),
+                Index::new(1, 2),
+                Index::new(2, 0),
+                Index::new(2, 1),
+                Index::new(2, 2),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn load_and_extract() {
+        let mut grid = Grid::empty_with_size(10, 8);
+        grid.load_pattern(&glider(), Index::new(4, 6)).unwrap();
+        assert_eq!(grid.count_ones(), 5);
+        assert!(grid.get_cell(Index::new(6, 8).to_offset(10)).unwrap().alive);
+
+        let mut extracted = Pattern::from_grid(&grid);
+        assert_eq!(extracted.rule, Some(Rule::conway()));
+        extracted.rule = None;
+        assert_eq!(extracted, glider());
+    }
+
+    #[test]
+    fn load_replaces_covered_cells() {
+        let mut grid = Grid::empty_with_size(3, 3);
+        grid.set_cell(0usize, true).unwrap();
+        grid.load_pattern(&glider(), Index::new(0, 0)).unwrap();
+        assert_eq!(grid.count_ones(), 5);
+        assert!(!grid.get_cell(0usize).unwrap().alive);
+    }
+
+    #[test]
+    fn too_large() {
+        let mut grid = Grid::empty_with_size(4, 4);
+        assert!(grid.load_pattern(&glider(), Index::new(1, 1)).is_ok());
+
+        for &offset in &[Index::new(2, 0), Index::new(0, 2), Index::new(40, 40)] {
+            match grid.load_pattern(&glider(), offset) {
+                Err(GameError::PatternTooLarge { width, height }) => {
+                    assert_eq!((width, height), (3, 3))
+                }
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn offset_overflow() {
+        let mut grid = Grid::empty_with_size(4, 4);
+        for &offset in &[Index::new(usize::MAX, 0), Index::new(0, usize::MAX)] {
+            match grid.load_pattern(&glider(), offset) {
+                Err(GameError::PatternTooLarge { width, height }) => {
+                    assert_eq!((width, height), (3, 3))
+                }
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn size_overflow() {
+        let pattern = Pattern {
+            width: usize::MAX,
+            height: 2,
+            ..glider()
+        };
+        let mut grid = Grid::empty_with_size(4, 4);
+        match grid.load_pattern(&pattern, Index::new(0, 0)) {
+            Err(GameError::PatternTooLarge { width, height }) => {
+                assert_eq!((width, height), (usize::MAX, 2))
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn formats_of_the_files() {
+        assert_eq!(
+            PatternFormat::from_file_name("glider.rle"),
+            PatternFormat::Rle
+        );
+        assert_eq!(
+            PatternFormat::from_file_name("glider.cells"),
+            PatternFormat::Plaintext
+        );
+        assert_eq!(
+            PatternFormat::from_file_name("Glider.LIF"),
+            PatternFormat::Life106
+        );
+        assert_eq!(PatternFormat::from_file_name("glider"), PatternFormat::Rle);
+
+        let mut grid = Grid::empty_with_size(10, 10);
+        grid.load_pattern(&glider(), Index::new(2, 2)).unwrap();
+        for format in PatternFormat::iter() {
+            let pattern = format.parse(&format.write_grid(&grid).unwrap()).unwrap();
+            assert_eq!((pattern.width, pattern.height), (3, 3));
+            assert_eq!(pattern.cells.len(), 5);
+        }
+    }
+
+    #[test]
+    fn empty_grid() {
+        let pattern = Pattern::from_grid(&Grid::empty_with_size(5, 5));
+        assert_eq!((pattern.width, pattern.height), (0, 0));
+        assert!(pattern.cells.is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! Plaintext patterns, usually stored in the `.cells` files:
+//!
+//! ```text
+//! !Name: Glider
+//! !The smallest spaceship.
+//! .O.
+//! ..O
+//! OOO
+//! ```
+//!
+//! Lines starting with `!` are comments, `.` is a dead cell and `O` is an alive cell.
+
+use super::{invalid, Pattern};
+use crate::conway::{GameError, Grid, Index, Result};
+
+/// Largest number of cells written, each of them takes a character
+const MAX_CELLS: usize = 1 << 24;
+
+pub fn parse(input: &str) -> Result<Pattern> {
+    let mut pattern = Pattern::default();
+    let mut row = 0;
+
+    for (line_idx, line) in input.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let line = line.trim_end();
+
+        if let Some(comment) = line.strip_prefix('!') {
+            match comment.strip_prefix("Name:") {
+                Some(name) => pattern.name = Some(name.trim().to_string()),
+                None => pattern.comments.push(comment.trim().to_string()),
+            }
+            continue;
+        }
+
+        for (col, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' => pattern.cells.push(Index::new(row, col)),
+                c => {
+                    return Err(invalid(
+                        line_number,
+                        format!("unexpected character '{}'", c),
+                    ))
+                }
+            }
+        }
+
+        // empty lines in between the rows are rows of dead cells
+        pattern.width = pattern.width.max(line.chars().count());
+        row += 1;
+        if !line.is_empty() {
+            pattern.height = row;
+        }
+    }
+
+    Ok(pattern)
+}
+
+/// Writes the pattern with a character for each of its cells. Patterns with more than
+/// `MAX_CELLS` cells are rejected.
+pub fn write(pattern: &Pattern) -> Result<String> {
+    let too_large = || GameError::PatternTooLarge {
+        width: pattern.width,
+        height: pattern.height,
+    };
+    let size = pattern
+        .width
+        .checked_mul(pattern.height)
+        .ok_or_else(too_large)?;
+    if size > MAX_CELLS {
+        return Err(too_large());
+    }
+
+    let mut output = String::new();
+
+    if let Some(name) = &pattern.name {
+        output.push_str(&format!("!Name: {}\n", name));
+    }
+    for comment in &pattern.comments {
+        output.push_str(&format!("!{}\n", comment));
+    }
+
+    // the cells outside of the declared size are dropped, as there is no room for them
+    let mut rows = vec![vec!['.'; pattern.width]; pattern.height];
+    for index in &pattern.cells {
+        if index.row < pattern.height && index.col < pattern.width {
+            rows[index.row][index.col] = 'O';
+        }
+    }
+    for row in rows {
+        output.extend(row);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+/// Exports the live region of the grid
+pub fn write_grid(grid: &Grid) -> Result<String> {
+    write(&Pattern::from_grid(grid))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::assert_grid_round_trip;
+    use super::*;
+    use crate::conway::GameError;
+
+    const GLIDER: &str = "!Name: Glider
+!The smallest, most common, and first-discovered spaceship.
+.O.
+..O
+OOO
+";
+
+    #[test]
+    fn parse_glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments.len(), 1);
+        assert_eq!(pattern.rule, None);
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(
+            pattern.cells,
+            vec![
+                Index::new(0, 1),
+                Index::new(1, 2),
+                Index::new(2, 0),
+                Index::new(2, 1),
+                Index::new(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn short_and_empty_rows() {
+        let pattern = parse("O\n\n..O\n\n").unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, vec![Index::new(0, 0), Index::new(2, 2)]);
+        assert_eq!(write(&pattern).unwrap(), "O..\n...\n..O\n");
+    }
+
+    #[test]
+    fn round_trip() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(write(&pattern).unwrap(), GLIDER);
+        assert_eq!(parse(&write(&pattern).unwrap()).unwrap(), pattern);
+    }
+
+    #[test]
+    fn grid_round_trip() {
+        assert_grid_round_trip(parse, |grid| write_grid(grid).unwrap());
+    }
+
+    #[test]
+    fn too_large() {
+        let pattern = Pattern {
+            width: usize::MAX,
+            height: 2,
+            cells: vec![Index::new(0, 0)],
+            ..Default::default()
+        };
+        match write(&pattern) {
+            Err(GameError::PatternTooLarge { width, height }) => {
+                assert_eq!((width, height), (usize::MAX, 2))
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn cells_outside_of_the_size() {
+        let pattern = Pattern {
+            width: 2,
+            height: 2,
+            cells: vec![Index::new(0, 0), Index::new(0, 2), Index::new(5, 1)],
+            ..Default::default()
+        };
+        assert_eq!(write(&pattern).unwrap(), "O.\n..\n");
+    }
+
+    #[test]
+    fn invalid_character() {
+        match parse("!Name: broken\n.O.\n.X.\n") {
+            Err(GameError::InvalidPattern { line, .. }) => assert_eq!(line, 3),
+            other => panic!("{:?}", other),
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Run Length Encoded patterns, the format used by LifeWiki and Golly:
+//!
+//! ```text
+//! #N Glider
+//! #C The smallest spaceship.
+//! x = 3, y = 3, rule = B3/S23
+//! bob$2bo$3o!
+//! ```
+//!
+//! `b` is a dead cell, `o` is an alive cell, `$` ends a row and `!` ends the pattern. Each of
+//! them can be preceded by a number of repetitions.
+
+use super::{invalid, Pattern};
+use crate::conway::{Grid, Index, Result};
+
+/// Maximal length of the lines of the written patterns
+const LINE_LENGTH: usize = 70;
+
+pub fn parse(input: &str) -> Result<Pattern> {
+    let mut pattern = Pattern::default();
+    let mut header_found = false;
+    let mut finished = false;
+
+    let mut row = 0;
+    let mut col: usize = 0;
+    let mut count: Option<usize> = None;
+
+    for (line_idx, line) in input.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let line = line.trim();
+
+        if finished || line.is_empty() {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            let mut chars = comment.chars();
+            let kind = chars.next();
+            let text = chars.as_str().trim().to_string();
+            match kind {
+                Some('N') => pattern.name = Some(text),
+                Some('C') | Some('c') | Some('O') => pattern.comments.push(text),
+                // offsets and other Golly specific lines don't matter for us
+                _ => {}
+            }
+            continue;
+        }
+
+        if !header_found {
+            parse_header(line, line_number, &mut pattern)?;
+            header_found = true;
+            continue;
+        }
+
+        for c in line.chars() {
+            match c {
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    count = Some(
+                        count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(digit))
+                            .ok_or_else(|| invalid(line_number, "run count is too big"))?,
+                    );
+                }
+                'b' | 'o' => {
+                    let run = count.take().unwrap_or(1);
+                    let end = col.checked_add(run).filter(|&end| end <= pattern.width);
+                    if row >= pattern.height || end.is_none() {
+                        return Err(invalid(line_number, "cells outside of the declared size"));
+                    }
+                    if c == 'o' {
+                        pattern
+                            .cells
+                            .extend((col..col + run).map(|col| Index::new(row, col)));
+                    }
+                    col += run;
+                }
+                '$' => {
+                    row += count.take().unwrap_or(1);
+                    col = 0;
+                }
+                '!' => {
+                    finished = true;
+                    break;
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    return Err(invalid(
+                        line_number,
+                        format!("unexpected character '{}'", c),
+                    ))
+                }
+            }
+        }
+    }
+
+    if !header_found {
+        return Err(invalid(0, "missing header"));
+    }
+    if !finished {
+        return Err(invalid(input.lines().count(), "missing '!' at the end"));
+    }
+
+    Ok(pattern)
+}
+
+/// Parses the `x = 3, y = 3, rule = B3/S23` line, the rule is optional
+fn parse_header(line: &str, line_number: usize, pattern: &mut Pattern) -> Result<()> {
+    let mut width = None;
+    let mut height = None;
+
+    for part in line.split(',') {
+        let mut key_value = part.splitn(2, '=');
+        let key = key_value.next().unwrap_or("").trim();
+        let value = key_value
+            .next()
+            .ok_or_else(|| invalid(line_number, format!("expected '=' in '{}'", part.trim())))?
+            .trim();
+
+        let size = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| invalid(line_number, format!("invalid size '{}'", value)))
+        };
+
+        match key {
+            "x" => width = Some(size()?),
+            "y" => height = Some(size()?),
+            "rule" => {
+                pattern.rule = Some(
+                    value
+                        .parse()
+                        .map_err(|_| invalid(line_number, format!("invalid rule '{}'", value)))?,
+                )
+            }
+            _ => {
+                return Err(invalid(
+                    line_number,
+                    format!("unknown header field '{}'", key),
+                ))
+            }
+        }
+    }
+
+    pattern.width = width.ok_or_else(|| invalid(line_number, "missing 'x' in the header"))?;
+    pattern.height = height.ok_or_else(|| invalid(line_number, "missing 'y' in the header"))?;
+
+    Ok(())
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let mut output = String::new();
+
+    if let Some(name) = &pattern.name {
+        output.push_str(&format!("#N {}\n", name));
+    }
+    for comment in &pattern.comments {
+        output.push_str(&format!("#C {}\n", comment));
+    }
+    output.push_str(&format!("x = {}, y = {}", pattern.width, pattern.height));
+    if let Some(rule) = pattern.rule {
+        output.push_str(&format!(", rule = {}", rule));
+    }
+    output.push('\n');
+
+    // the header may declare any size, so the runs are built from the cells alone
+    let mut cells: Vec<Index> = pattern
+        .cells
+        .iter()
+        .copied()
+        .filter(|index| index.row < pattern.height && index.col < pattern.width)
+        .collect();
+    cells.sort_by_key(|index| (index.row, index.col));
+    cells.dedup();
+
+    // runs of (count, tag), dead cells at the ends of the rows and empty rows at the end are
+    // left out
+    let mut runs: Vec<(usize, char)> = Vec::new();
+
+    let (mut row, mut col) = (0, 0);
+    for index in cells {
+        if index.row > row {
+            push_run(&mut runs, '$', index.row - row);
+            row = index.row;
+            col = 0;
+        }
+        if index.col > col {
+            push_run(&mut runs, 'b', index.col - col);
+        }
+        push_run(&mut runs, 'o', 1);
+        col = index.col + 1;
+    }
+    runs.push((1, '!'));
+
+    let mut line_length = 0;
+    for (count, tag) in runs {
+        let run = if count == 1 {
+            tag.to_string()
+        } else {
+            format!("{}{}", count, tag)
+        };
+        if line_length + run.len() > LINE_LENGTH {
+            output.push('\n');
+            line_length = 0;
+        }
+        line_length += run.len();
+        output.push_str(&run);
+    }
+    output.push('\n');
+
+    output
+}
+
+/// Appends the cells to the last run if it has the same tag
+fn push_run(runs: &mut Vec<(usize, char)>, tag: char, count: usize) {
+    match runs.last_mut() {
+        Some((last_count, last_tag)) if *last_tag == tag => *last_count += count,
+        _ => runs.push((count, tag)),
+    }
+}
+
+/// Exports the live region of the grid
+pub fn write_grid(grid: &Grid) -> String {
+    write(&Pattern::from_grid(grid))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conway::{GameError, Rule};
+
+    const GLIDER: &str = "#N Glider
+#C The smallest, most common, and first-discovered spaceship.
+#C www.conwaylife.com/wiki/index.php?title=Glider
+x = 3, y = 3, rule = B3/S23
+bob$2bo$3o!
+";
+
+    const GOSPER_GUN: &str = "#N Gosper glider gun
+x = 36, y = 9, rule = B3/S23
+24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b
+obo$10bo5bo7bo$11bo3bo$12b2o!
+";
+
+    #[test]
+    fn parse_glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!(pattern.name.as_deref(), Some("Glider"));
+        assert_eq!(pattern.comments.len(), 2);
+        assert_eq!(pattern.rule, Some(Rule::conway()));
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(
+            pattern.cells,
+            vec![
+                Index::new(0, 1),
+                Index::new(1, 2),
+                Index::new(2, 0),
+                Index::new(2, 1),
+                Index::new(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_multiline_body() {
+        let pattern = parse(GOSPER_GUN).unwrap();
+        assert_eq!((pattern.width, pattern.height), (36, 9));
+        assert_eq!(pattern.cells.len(), 36);
+    }
+
+    #[test]
+    fn round_trip() {
+        for input in &[GLIDER, GOSPER_GUN] {
+            let pattern = parse(input).unwrap();
+            assert_eq!(parse(&write(&pattern)).unwrap(), pattern);
+        }
+        assert_eq!(
+            write(&parse(GLIDER).unwrap()).lines().last(),
+            Some("bo$2bo$3o!")
+        );
+    }
+
+    #[test]
+    fn empty_rows() {
+        let pattern = parse("x = 2, y = 5\no3$o!").unwrap();
+        assert_eq!(pattern.cells, vec![Index::new(0, 0), Index::new(3, 0)]);
+        assert_eq!(write(&pattern), "x = 2, y = 5\no3$o!\n");
+    }
+
+    #[test]
+    fn huge_declared_size() {
+        let input = format!("x = {0}, y = {0}\n$2bo!", usize::MAX);
+        let pattern = parse(&input).unwrap();
+        assert_eq!(pattern.cells, vec![Index::new(1, 2)]);
+        assert_eq!(
+            write(&pattern),
+            format!("{}\n$2bo!\n", input.lines().next().unwrap())
+        );
+
+        let overflowing = format!("x = {0}, y = 1\n{0}b2o!", usize::MAX);
+        assert!(parse(&overflowing).is_err());
+    }
+
+    #[test]
+    fn load_and_export_grid() {
+        let mut grid = Grid::empty_with_size(50, 50);
+        grid.load_pattern(&parse(GOSPER_GUN).unwrap(), Index::new(10, 5))
+            .unwrap();
+        assert_eq!(grid.count_ones(), 36);
+
+        let exported = parse(&write_grid(&grid)).unwrap();
+        assert_eq!(exported.cells, parse(GOSPER_GUN).unwrap().cells);
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        let cases = [
+            ("bob$2bo$3o!", 1),
+            ("x = 3\nbo!", 1),
+            ("x = 3, y = 3, rule = B9/S23\nbo!", 1),
+            ("x = 3, y = 3\nbob$2bo$3o", 2),
+            ("x = 3, y = 3\nbob$2bo$3q!", 2),
+            ("x = 3, y = 3\n#C fine\nbob$4o!", 3),
+            ("x = 3, y = 1\nbob$o!", 2),
+            ("", 0),
+        ];
+        for &(input, expected_line) in &cases {
+            match parse(input) {
+                Err(GameError::InvalidPattern { line, .. }) => {
+                    assert_eq!(line, expected_line, "{:?}", input)
+                }
+                other => panic!("{:?} parsed as {:?}", input, other),
+            }
+        }
+    }
+}
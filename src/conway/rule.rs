@@ -0,0 +1,264 @@
+//! Life-like rules written in the B/S notation, e.g. "B3/S23" for the Conway's Game of Life.
+//! The digits after `B` are the numbers of alive neighbors that cause a dead cell to be born,
+//! the digits after `S` are the numbers of alive neighbors that let an alive cell survive.
+//!
+//! Rules of the Generations family add the number of states, e.g. "B2/S/C3" for the Brian's
+//! Brain. Cells that don't survive aren't dead right away, they go through the dying states first.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::cell::Cell;
+use super::GameError;
+
+/// Maximal number of neighbors a cell can have
+const MAX_NEIGHBORS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule {
+    birth: [bool; MAX_NEIGHBORS + 1],
+    survival: [bool; MAX_NEIGHBORS + 1],
+    /// Number of states of a cell, including alive and dead
+    states: u8,
+}
+
+impl Rule {
+    /// B3/S23
+    pub fn conway() -> Self {
+        Self::from_counts(&[3], &[2, 3])
+    }
+
+    /// B36/S23
+    pub fn highlife() -> Self {
+        Self::from_counts(&[3, 6], &[2, 3])
+    }
+
+    /// B2/S
+    pub fn seeds() -> Self {
+        Self::from_counts(&[2], &[])
+    }
+
+    /// B3678/S34678
+    pub fn day_and_night() -> Self {
+        Self::from_counts(&[3, 6, 7, 8], &[3, 4, 6, 7, 8])
+    }
+
+    /// B2/S/C3
+    pub fn brians_brain() -> Self {
+        Self {
+            states: 3,
+            ..Self::from_counts(&[2], &[])
+        }
+    }
+
+    /// B2/S345/C4
+    pub fn star_wars() -> Self {
+        Self {
+            states: 4,
+            ..Self::from_counts(&[2], &[3, 4, 5])
+        }
+    }
+
+    fn from_counts(birth: &[usize], survival: &[usize]) -> Self {
+        let mut rule = Self {
+            birth: [false; MAX_NEIGHBORS + 1],
+            survival: [false; MAX_NEIGHBORS + 1],
+            states: 2,
+        };
+        birth.iter().for_each(|&n| rule.birth[n] = true);
+        survival.iter().for_each(|&n| rule.survival[n] = true);
+        rule
+    }
+
+    /// Number of states of a cell, 2 for the rules without dying states
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    /// Whether a dead cell with `neighbors` alive neighbors becomes alive
+    pub fn is_born(&self, neighbors: usize) -> bool {
+        self.birth.get(neighbors).copied().unwrap_or(false)
+    }
+
+    /// Whether an alive cell with `neighbors` alive neighbors stays alive
+    pub fn survives(&self, neighbors: usize) -> bool {
+        self.survival.get(neighbors).copied().unwrap_or(false)
+    }
+
+    /// Returns whether the cell is alive in the next generation
+    pub fn next_state(&self, alive: bool, neighbors: usize) -> bool {
+        if alive {
+            self.survives(neighbors)
+        } else {
+            self.is_born(neighbors)
+        }
+    }
+
+    /// Returns the cell in the next generation. Dying cells can't be born, they keep decaying
+    /// until they are dead.
+    pub fn next_cell(&self, cell: Cell, neighbors: usize) -> Cell {
+        let alive = cell.decay == 0 && self.next_state(cell.alive, neighbors);
+        cell.next(alive, self.states)
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+impl FromStr for Rule {
+    type Err = GameError;
+
+    /// Parses rules like "B36/S23" or "B2/S345/C4". The order of the parts and the case of the
+    /// letters don't matter, so "s23/b36" is the same rule.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || GameError::InvalidRule(s.to_string());
+
+        let mut birth: Option<[bool; MAX_NEIGHBORS + 1]> = None;
+        let mut survival: Option<[bool; MAX_NEIGHBORS + 1]> = None;
+        let mut states: Option<u8> = None;
+
+        for part in s.trim().split('/') {
+            let mut chars = part.trim().chars();
+            let counts = match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') if birth.is_none() => birth.get_or_insert([false; MAX_NEIGHBORS + 1]),
+                Some('S') if survival.is_none() => {
+                    survival.get_or_insert([false; MAX_NEIGHBORS + 1])
+                }
+                Some('C') if states.is_none() => {
+                    let count = chars.as_str().parse::<u8>().map_err(|_| invalid())?;
+                    if count < 2 {
+                        return Err(invalid());
+                    }
+                    states = Some(count);
+                    continue;
+                }
+                _ => return Err(invalid()),
+            };
+
+            for c in chars {
+                let n = c
+                    .to_digit(10)
+                    .filter(|&n| n as usize <= MAX_NEIGHBORS)
+                    .ok_or_else(invalid)?;
+                counts[n as usize] = true;
+            }
+        }
+
+        Ok(Self {
+            birth: birth.ok_or_else(invalid)?,
+            survival: survival.ok_or_else(invalid)?,
+            states: states.unwrap_or(2),
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B")?;
+        for n in (0..=MAX_NEIGHBORS).filter(|&n| self.birth[n]) {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "/S")?;
+        for n in (0..=MAX_NEIGHBORS).filter(|&n| self.survival[n]) {
+            write!(f, "{}", n)?;
+        }
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_known_rules() {
+        assert_eq!("B3/S23".parse::<Rule>().unwrap(), Rule::conway());
+        assert_eq!("B36/S23".parse::<Rule>().unwrap(), Rule::highlife());
+        assert_eq!("B2/S".parse::<Rule>().unwrap(), Rule::seeds());
+        assert_eq!(
+            "B3678/S34678".parse::<Rule>().unwrap(),
+            Rule::day_and_night()
+        );
+        assert_eq!("s23/b3".parse::<Rule>().unwrap(), Rule::conway());
+        assert_eq!("B2/S/C3".parse::<Rule>().unwrap(), Rule::brians_brain());
+        assert_eq!("c4/S345/b2".parse::<Rule>().unwrap(), Rule::star_wars());
+        assert_eq!("B3/S23/C2".parse::<Rule>().unwrap(), Rule::conway());
+    }
+
+    #[test]
+    fn parse_invalid_rules() {
+        for invalid in &[
+            "",
+            "B3",
+            "S23",
+            "B39/S23",
+            "B3/S23/B3",
+            "X3/S23",
+            "B3/S2a",
+            "23/3",
+            "B2/S/C1",
+            "B2/S/C",
+            "B2/S/C3/C3",
+            "B2/S/C256",
+        ] {
+            match invalid.parse::<Rule>() {
+                Err(GameError::InvalidRule(rule)) => assert_eq!(&rule, invalid),
+                other => panic!("{:?} parsed as {:?}", invalid, other),
+            }
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for rule in &[
+            Rule::conway(),
+            Rule::highlife(),
+            Rule::seeds(),
+            Rule::day_and_night(),
+            Rule::brians_brain(),
+            Rule::star_wars(),
+        ] {
+            assert_eq!(rule.to_string().parse::<Rule>().unwrap(), *rule);
+        }
+        assert_eq!(Rule::seeds().to_string(), "B2/S");
+        assert_eq!(Rule::star_wars().to_string(), "B2/S345/C4");
+    }
+
+    #[test]
+    fn next_state() {
+        let rule = Rule::highlife();
+        assert!(rule.next_state(false, 6));
+        assert!(!rule.next_state(true, 6));
+        assert!(rule.next_state(true, 2));
+        assert!(!rule.next_state(false, 2));
+    }
+
+    #[test]
+    fn dying_states() {
+        let rule = Rule::star_wars();
+        let cell: Cell = true.into();
+
+        let cell = rule.next_cell(cell, 2);
+        assert!(!cell.alive && cell.just_changed);
+        assert_eq!((cell.decay, cell.age), (1, 0));
+
+        // dying cells can't be born
+        let cell = rule.next_cell(cell, 2);
+        assert!(!cell.alive && !cell.just_changed);
+        assert_eq!((cell.decay, cell.age), (2, 1));
+
+        let cell = rule.next_cell(cell, 3);
+        assert_eq!((cell.decay, cell.age), (0, 2));
+        assert!(rule.next_cell(cell, 2).alive);
+
+        // without dying states cells die right away
+        let cell = Rule::conway().next_cell(true.into(), 0);
+        assert_eq!((cell.alive, cell.decay), (false, 0));
+    }
+}
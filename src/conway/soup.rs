@@ -0,0 +1,200 @@
+//! Random soups generated from a seed, so that any random board can be recreated exactly.
+
+use rand::prelude::Rng;
+use rand::SeedableRng;
+use rand_pcg::Pcg32;
+use strum_macros::{Display, EnumIter, EnumString};
+
+use super::index::Index;
+
+/// Probability of a cell being alive in the soups created without a specified density
+pub const DEFAULT_DENSITY: f64 = 1.2 / 3.0;
+
+/// Symmetry of a soup around the center of the grid
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, EnumString, Display)]
+pub enum Symmetry {
+    None,
+    /// Rotating the grid by 180 degrees doesn't change it
+    C2,
+    /// Rotating the grid by 90 degrees doesn't change it
+    C4,
+    /// Rotating the grid by 90 degrees or mirroring it along any of its axes or diagonals doesn't
+    /// change it
+    D4,
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Symmetry::None
+    }
+}
+
+impl Symmetry {
+    /// Returns the cells, that the symmetry maps the cell at `index` onto, including the cell
+    /// itself. In grids that aren't square some of them don't land on a cell of the grid, these
+    /// are left out.
+    pub fn orbit(self, index: Index, width: usize, height: usize) -> Vec<Index> {
+        // coordinates relative to the center of the grid, doubled to avoid halves
+        let x = 2 * index.col as isize - (width as isize - 1);
+        let y = 2 * index.row as isize - (height as isize - 1);
+
+        let images: &[(isize, isize)] = match self {
+            Symmetry::None => &[(x, y)],
+            Symmetry::C2 => &[(x, y), (-x, -y)],
+            Symmetry::C4 => &[(x, y), (-y, x), (-x, -y), (y, -x)],
+            Symmetry::D4 => &[
+                (x, y),
+                (-y, x),
+                (-x, -y),
+                (y, -x),
+                (-x, y),
+                (x, -y),
+                (y, x),
+                (-y, -x),
+            ],
+        };
+
+        let mut orbit: Vec<Index> = images
+            .iter()
+            .filter_map(|&(x, y)| {
+                let col = x + width as isize - 1;
+                let row = y + height as isize - 1;
+                if col < 0 || row < 0 || col % 2 != 0 || row % 2 != 0 {
+                    return None;
+                }
+                let index = Index::new(row as usize / 2, col as usize / 2);
+                if index.row < height && index.col < width {
+                    Some(index)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        orbit.sort_by_key(|index| index.to_offset(width));
+        orbit.dedup();
+        orbit
+    }
+}
+
+/// Everything needed to generate the same soup again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Soup {
+    pub seed: u64,
+    /// Probability of a cell being alive, between 0 and 1
+    pub density: f64,
+    pub symmetry: Symmetry,
+}
+
+impl Soup {
+    /// Creates a soup without any symmetry. The density is clamped between 0 and 1.
+    pub fn new(seed: u64, density: f64) -> Self {
+        Self {
+            seed,
+            density: density.max(0.0).min(1.0),
+            symmetry: Symmetry::None,
+        }
+    }
+
+    pub fn with_symmetry(self, symmetry: Symmetry) -> Self {
+        Self { symmetry, ..self }
+    }
+
+    /// Generates the states of the cells of a grid of the given size. The same soup always
+    /// generates the same cells.
+    pub fn generate(&self, width: usize, height: usize) -> Vec<bool> {
+        let mut rng = Pcg32::seed_from_u64(self.seed);
+        let mut cells = vec![None; width * height];
+
+        for idx in 0..cells.len() {
+            if cells[idx].is_some() {
+                continue;
+            }
+            let alive = rng.gen_bool(self.density);
+            for index in self
+                .symmetry
+                .orbit(Index::from_offset(idx, width), width, height)
+            {
+                cells[index.to_offset(width)] = Some(alive);
+            }
+        }
+
+        cells
+            .into_iter()
+            .map(|cell| cell.unwrap_or(false))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn is_symmetric(
+        cells: &[bool],
+        size: usize,
+        map: impl Fn(usize, usize) -> (usize, usize),
+    ) -> bool {
+        (0..size * size).all(|idx| {
+            let (row, col) = map(idx / size, idx % size);
+            cells[idx] == cells[row * size + col]
+        })
+    }
+
+    #[test]
+    fn same_seed_same_soup() {
+        let soup = Soup::new(42, DEFAULT_DENSITY);
+        assert_eq!(soup.generate(30, 20), soup.generate(30, 20));
+        assert_ne!(
+            soup.generate(30, 20),
+            Soup::new(43, DEFAULT_DENSITY).generate(30, 20)
+        );
+    }
+
+    #[test]
+    fn density() {
+        let count = |density| {
+            Soup::new(7, density)
+                .generate(100, 100)
+                .iter()
+                .filter(|&&alive| alive)
+                .count()
+        };
+        assert_eq!(count(0.0), 0);
+        assert_eq!(count(1.0), 100 * 100);
+        assert_eq!(count(-3.0), 0);
+        assert!((4500..5500).contains(&count(0.5)));
+    }
+
+    #[test]
+    fn symmetries() {
+        let size = 21;
+        let last = size - 1;
+        for &symmetry in &[Symmetry::C2, Symmetry::C4, Symmetry::D4] {
+            let cells = Soup::new(3, 0.5)
+                .with_symmetry(symmetry)
+                .generate(size, size);
+            assert!(is_symmetric(&cells, size, |r, c| (last - r, last - c)));
+            if symmetry != Symmetry::C2 {
+                assert!(is_symmetric(&cells, size, |r, c| (c, last - r)));
+            }
+            if symmetry == Symmetry::D4 {
+                assert!(is_symmetric(&cells, size, |r, c| (r, last - c)));
+                assert!(is_symmetric(&cells, size, |r, c| (c, r)));
+            } else {
+                assert!(!is_symmetric(&cells, size, |r, c| (r, last - c)));
+            }
+        }
+    }
+
+    #[test]
+    fn orbits_in_non_square_grid() {
+        // 4x3 grid: rotating by 90 degrees never lands on a cell
+        assert_eq!(
+            Symmetry::C4.orbit(Index::new(0, 0), 4, 3),
+            vec![Index::new(0, 0), Index::new(2, 3)]
+        );
+        // 5x3 grid: the rotations of the corner fall outside of the grid
+        assert_eq!(Symmetry::C4.orbit(Index::new(1, 1), 5, 3).len(), 4);
+        assert_eq!(Symmetry::C4.orbit(Index::new(0, 0), 5, 3).len(), 2);
+    }
+}
@@ -0,0 +1,129 @@
+//! Detection of grids that stopped evolving, either because all the cells died, or because they
+//! keep repeating the same generations.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+
+use strum_macros::{Display, EnumIter, EnumString};
+
+use super::cell::Cell;
+
+/// Number of past generations remembered, it is also the longest period that can be detected
+pub const HISTORY_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stability {
+    /// None of the remembered generations repeated
+    Evolving,
+    /// All the cells are dead
+    Empty,
+    /// The generation is the same as the previous one
+    Static,
+    /// The generation repeats after the given number of generations
+    Periodic(usize),
+}
+
+impl Default for Stability {
+    fn default() -> Self {
+        Stability::Evolving
+    }
+}
+
+/// What to do with a grid that stopped evolving
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, EnumString, Display)]
+pub enum Policy {
+    /// Leave the grid alone
+    Continue,
+    /// Stop the simulation of the grid
+    Stop,
+    /// Fill the grid with a new soup
+    Reseed,
+    /// Flip a few random cells of the grid
+    InjectNoise,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::Continue
+    }
+}
+
+/// Policies for each of the ways a grid can stop evolving
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StabilityPolicy {
+    pub empty: Policy,
+    pub still: Policy,
+    pub periodic: Policy,
+}
+
+impl StabilityPolicy {
+    /// Uses the same policy for all the cases
+    pub fn all(policy: Policy) -> Self {
+        Self {
+            empty: policy,
+            still: policy,
+            periodic: policy,
+        }
+    }
+
+    pub fn policy_for(&self, stability: Stability) -> Policy {
+        match stability {
+            Stability::Evolving => Policy::Continue,
+            Stability::Empty => self.empty,
+            Stability::Static => self.still,
+            Stability::Periodic(_) => self.periodic,
+        }
+    }
+}
+
+/// Hashes of the last generations of a grid, the most recent one is at the back
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    hashes: VecDeque<u64>,
+    stability: Stability,
+}
+
+impl History {
+    pub fn stability(&self) -> Stability {
+        self.stability
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Forgets all the generations, e.g. after the grid was edited
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+        self.stability = Stability::Evolving;
+    }
+
+    /// Remembers the generation and updates the stability
+    pub fn push(&mut self, cells: &[Cell]) {
+        let hash = Self::hash(cells);
+
+        self.stability = if cells.iter().all(|cell| !cell.alive && cell.decay == 0) {
+            Stability::Empty
+        } else {
+            match self.hashes.iter().rev().position(|&past| past == hash) {
+                Some(0) => Stability::Static,
+                Some(distance) => Stability::Periodic(distance + 1),
+                None => Stability::Evolving,
+            }
+        };
+
+        if self.hashes.len() == HISTORY_LENGTH {
+            self.hashes.pop_front();
+        }
+        self.hashes.push_back(hash);
+    }
+
+    fn hash(cells: &[Cell]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for cell in cells {
+            (cell.alive, cell.decay).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
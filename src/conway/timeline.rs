@@ -0,0 +1,189 @@
+//! Bounded history of a grid. Each frame stores only the cells that changed, so rewinding a
+//! board costs a fraction of the memory of keeping whole copies of it.
+
+use std::collections::VecDeque;
+
+use super::cell::Cell;
+
+/// Default maximal number of frames kept in the timeline of a grid
+pub const TIMELINE_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameKind {
+    /// The grid moved to one of the next generations
+    Generation,
+    /// Cells of the grid were changed by hand
+    Edit,
+}
+
+/// Cell at the `offset` before and after the frame
+#[derive(Debug, Clone, Copy)]
+pub struct Change {
+    pub offset: usize,
+    pub before: Cell,
+    pub after: Cell,
+}
+
+impl Change {
+    /// Whether the cell became alive or died, rather than just decayed
+    pub fn just_changed(&self) -> bool {
+        self.before.alive != self.after.alive
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub kind: FrameKind,
+    /// Number of generations the grid moved by, 1 unless it was fast-forwarded and 0 for edits
+    pub generations: u64,
+    /// Only the cells that became alive, died or started decaying. The other cells of a
+    /// generation frame just got older.
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    frames: VecDeque<Frame>,
+    /// Frames that were reverted and can be applied again, the last one is the next to apply
+    undone: Vec<Frame>,
+    capacity: usize,
+}
+
+impl Timeline {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            undone: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Records a new frame. The frames that were undone can't be applied again afterwards, and
+    /// the oldest frame is forgotten when the timeline is full.
+    pub fn push(&mut self, frame: Frame) {
+        self.undone.clear();
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    pub fn last(&self) -> Option<&Frame> {
+        self.frames.back()
+    }
+
+    /// Removes the last frame, so it can be reverted. It can be applied again with `redo`.
+    pub fn undo(&mut self) -> Option<Frame> {
+        let frame = self.frames.pop_back()?;
+        self.undone.push(frame.clone());
+        Some(frame)
+    }
+
+    /// Returns the last undone frame, so it can be applied again
+    pub fn redo(&mut self) -> Option<Frame> {
+        let frame = self.undone.pop()?;
+        self.frames.push_back(frame.clone());
+        Some(frame)
+    }
+
+    /// Next frame `redo` would return
+    pub fn next_undone(&self) -> Option<&Frame> {
+        self.undone.last()
+    }
+
+    /// Number of generations that can be reverted
+    pub fn past_generations(&self) -> u64 {
+        count_generations(self.frames.iter())
+    }
+
+    /// Number of generations that can be applied again
+    pub fn future_generations(&self) -> u64 {
+        count_generations(self.undone.iter())
+    }
+
+    /// Number of generations the next undone generation frame moves the grid by
+    pub fn next_undone_generations(&self) -> Option<u64> {
+        self.undone
+            .iter()
+            .rev()
+            .find(|frame| frame.kind == FrameKind::Generation)
+            .map(|frame| frame.generations)
+    }
+}
+
+fn count_generations<'f>(frames: impl Iterator<Item = &'f Frame>) -> u64 {
+    frames.map(|frame| frame.generations).sum()
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new(TIMELINE_LENGTH)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(kind: FrameKind) -> Frame {
+        Frame {
+            kind,
+            generations: (kind == FrameKind::Generation) as u64,
+            changes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn bounded() {
+        let mut timeline = Timeline::new(3);
+        for _ in 0..5 {
+            timeline.push(frame(FrameKind::Generation));
+        }
+        assert_eq!(timeline.past_generations(), 3);
+
+        assert!(timeline.undo().is_some());
+        assert_eq!(timeline.past_generations(), 2);
+        assert_eq!(timeline.future_generations(), 1);
+
+        // a new frame makes the undone ones unreachable
+        timeline.push(frame(FrameKind::Edit));
+        assert_eq!(timeline.future_generations(), 0);
+        assert!(timeline.redo().is_none());
+    }
+
+    #[test]
+    fn redo_in_order() {
+        let mut timeline = Timeline::new(10);
+        timeline.push(frame(FrameKind::Generation));
+        timeline.push(frame(FrameKind::Edit));
+        timeline.undo();
+        timeline.undo();
+
+        assert_eq!(timeline.next_undone_generations(), Some(1));
+        assert_eq!(timeline.redo().unwrap().kind, FrameKind::Generation);
+        assert_eq!(timeline.next_undone().unwrap().kind, FrameKind::Edit);
+        assert_eq!(timeline.next_undone_generations(), None);
+        assert_eq!(timeline.redo().unwrap().kind, FrameKind::Edit);
+        assert!(timeline.redo().is_none());
+        assert_eq!(timeline.last().unwrap().kind, FrameKind::Edit);
+    }
+
+    #[test]
+    fn fast_forwarded_frames() {
+        let mut timeline = Timeline::new(10);
+        timeline.push(frame(FrameKind::Generation));
+        timeline.push(Frame {
+            generations: 1024,
+            ..frame(FrameKind::Generation)
+        });
+        assert_eq!(timeline.past_generations(), 1025);
+
+        timeline.undo();
+        assert_eq!(timeline.past_generations(), 1);
+        assert_eq!(timeline.future_generations(), 1024);
+        assert_eq!(timeline.next_undone_generations(), Some(1024));
+    }
+}
@@ -0,0 +1,79 @@
+//! Describes how the edges of a grid are glued together. The topology decides what the
+//! neighbors of the cells on the edges of the grid are.
+
+use super::index::Index;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    /// Everything outside of the grid is a dead cell
+    Bounded,
+    /// Left edge is glued to the right one and top edge is glued to the bottom one
+    Torus,
+    /// Left edge is glued to the right one, top edge is glued to the bottom one with a twist,
+    /// so crossing it mirrors the column
+    KleinBottle,
+    /// Both pairs of edges are glued with a twist, so crossing the top or bottom edge mirrors
+    /// the column and crossing the left or right edge mirrors the row
+    CrossSurface,
+    /// Left edge is glued to the right one, top and bottom edges are bounded
+    HorizontalCylinder,
+    /// Top edge is glued to the bottom one, left and right edges are bounded
+    VerticalCylinder,
+}
+
+impl Topology {
+    /// Maps a position, that may lie outside of the grid of the given size, onto the cell of the
+    /// grid it is glued to. Returns `None` if the position lies behind a bounded edge.
+    pub fn wrap(self, row: isize, col: isize, width: usize, height: usize) -> Option<Index> {
+        use Topology::*;
+
+        let width = width as isize;
+        let height = height as isize;
+
+        // (edges are glued, edges are twisted)
+        let (wrap_rows, twist_rows) = match self {
+            Bounded | HorizontalCylinder => (false, false),
+            Torus | VerticalCylinder => (true, false),
+            KleinBottle | CrossSurface => (true, true),
+        };
+        let (wrap_cols, twist_cols) = match self {
+            Bounded | VerticalCylinder => (false, false),
+            Torus | HorizontalCylinder | KleinBottle => (true, false),
+            CrossSurface => (true, true),
+        };
+
+        let mut row = row;
+        let mut col = col;
+
+        if col < 0 || col >= width {
+            if !wrap_cols {
+                return None;
+            }
+            col = col.rem_euclid(width);
+            if twist_cols {
+                row = height - 1 - row;
+            }
+        }
+
+        if row < 0 || row >= height {
+            if !wrap_rows {
+                return None;
+            }
+            row = row.rem_euclid(height);
+            if twist_rows {
+                col = width - 1 - col;
+            }
+        }
+
+        Some(Index {
+            row: row as usize,
+            col: col as usize,
+        })
+    }
+}
+
+impl Default for Topology {
+    fn default() -> Self {
+        Topology::Bounded
+    }
+}
@@ -0,0 +1,278 @@
+//! Unbounded sparse universe. Only the positions of the alive cells are stored, so patterns can
+//! travel arbitrarily far without falling off the edge. A fixed size `Grid` can be used as a
+//! viewport onto the universe for rendering and sonification.
+
+use std::collections::{HashMap, HashSet};
+
+use super::grid::Grid;
+use super::index::Index;
+use super::rule::Rule;
+use super::{GameError, Result};
+
+/// Position of a cell in the universe. It can be negative, unlike the `Index` of a grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub row: i64,
+    pub col: i64,
+}
+
+impl Position {
+    pub fn new(row: i64, col: i64) -> Self {
+        Self { row, col }
+    }
+
+    /// Position of the cell at the `index` of a grid with its top left corner at this position
+    pub fn offset(self, index: Index) -> Self {
+        Self::new(self.row + index.row as i64, self.col + index.col as i64)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Universe {
+    rule: Rule,
+    alive: HashSet<Position>,
+}
+
+impl Universe {
+    /// Creates an empty universe. Rules in which dead cells with no alive neighbors are born
+    /// would fill the whole unbounded plane, so they are rejected, just like the rules with dying
+    /// states.
+    pub fn new(rule: Rule) -> Result<Self> {
+        Self::check(rule)?;
+
+        Ok(Self {
+            rule,
+            alive: HashSet::new(),
+        })
+    }
+
+    /// Creates a universe with the alive cells of the grid, with the top left corner of the grid
+    /// placed at `origin`
+    pub fn from_grid(grid: &Grid, origin: Position) -> Result<Self> {
+        let mut universe = Self::new(grid.rule())?;
+        for (idx, cell) in grid.iter().enumerate() {
+            if cell.alive {
+                let index = Index::from_offset(idx, grid.width());
+                universe.set(origin.offset(index), true);
+            }
+        }
+        Ok(universe)
+    }
+
+    #[cfg(test)]
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Switches to the `rule`, unless it isn't supported by the universe
+    pub fn set_rule(&mut self, rule: Rule) -> Result<()> {
+        Self::check(rule)?;
+        self.rule = rule;
+        Ok(())
+    }
+
+    fn check(rule: Rule) -> Result<()> {
+        if rule.is_born(0) || rule.states() > 2 {
+            return Err(GameError::UnsupportedRule(rule.to_string()));
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn population(&self) -> usize {
+        self.alive.len()
+    }
+
+    pub fn is_alive(&self, position: Position) -> bool {
+        self.alive.contains(&position)
+    }
+
+    pub fn set(&mut self, position: Position, alive: bool) {
+        if alive {
+            self.alive.insert(position);
+        } else {
+            self.alive.remove(&position);
+        }
+    }
+
+    /// Returns iterator over the positions of the alive cells, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &Position> {
+        self.alive.iter()
+    }
+
+    pub fn next_gen(&mut self) {
+        // Only the alive cells and their neighbors can be alive in the next generation
+        let mut neighbors: HashMap<Position, usize> = HashMap::with_capacity(self.alive.len() * 8);
+        for position in &self.alive {
+            for row in -1..=1 {
+                for col in -1..=1 {
+                    if row == 0 && col == 0 {
+                        continue;
+                    }
+                    let neighbor = Position::new(position.row + row, position.col + col);
+                    *neighbors.entry(neighbor).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut next: HashSet<Position> = neighbors
+            .iter()
+            .filter(|(position, &count)| self.rule.next_state(self.is_alive(**position), count))
+            .map(|(&position, _)| position)
+            .collect();
+
+        // alive cells without any alive neighbors don't show up in the counts
+        if self.rule.survives(0) {
+            next.extend(
+                self.alive
+                    .iter()
+                    .filter(|position| !neighbors.contains_key(position)),
+            );
+        }
+
+        self.alive = next;
+    }
+
+    /// Returns the top left and bottom right corners (both inclusive) of the smallest rectangle
+    /// containing all the alive cells, or `None` if there are no alive cells
+    pub fn bounding_box(&self) -> Option<(Position, Position)> {
+        let mut positions = self.iter();
+        let first = *positions.next()?;
+
+        Some(positions.fold((first, first), |(min, max), position| {
+            (
+                Position::new(min.row.min(position.row), min.col.min(position.col)),
+                Position::new(max.row.max(position.row), max.col.max(position.col)),
+            )
+        }))
+    }
+
+    /// Copies the part of the universe with the top left corner at `origin` into the grid. The
+    /// grid marks the cells that differ from its previous contents as just changed, so it can be
+    /// sonified as if it was evolving on its own.
+    pub fn render_into(&self, grid: &mut Grid, origin: Position) {
+        let width = grid.width();
+        let cells = (0..width * grid.height()).map(|idx| {
+            let position = origin.offset(Index::from_offset(idx, width));
+            (idx, self.is_alive(position))
+        });
+        let _ = grid.set_cells(cells);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GLIDER: [(i64, i64); 5] = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+
+    fn glider() -> Universe {
+        let mut universe = Universe::new(Rule::conway()).unwrap();
+        for &(row, col) in &GLIDER {
+            universe.set(Position::new(row, col), true);
+        }
+        universe
+    }
+
+    #[test]
+    fn glider_travels_far() {
+        let mut universe = glider();
+        assert_eq!(
+            universe.bounding_box(),
+            Some((Position::new(0, 0), Position::new(2, 2)))
+        );
+
+        for _ in 0..400 {
+            universe.next_gen();
+        }
+
+        assert_eq!(universe.population(), 5);
+        assert_eq!(
+            universe.bounding_box(),
+            Some((Position::new(100, 100), Position::new(102, 102)))
+        );
+        for &(row, col) in &GLIDER {
+            assert!(universe.is_alive(Position::new(row + 100, col + 100)));
+        }
+    }
+
+    #[test]
+    fn matches_grid() {
+        let mut grid = Grid::empty_with_size(40, 40);
+        let soup = Grid::random_with_size(8, 8);
+        for (idx, cell) in soup.iter().enumerate() {
+            let index = Index::new(idx / 8 + 16, idx % 8 + 16);
+            grid.set_cell(index.to_offset(40), cell.alive).unwrap();
+        }
+        grid.start();
+
+        let origin = Position::new(-20, -20);
+        let mut universe = Universe::from_grid(&grid, origin).unwrap();
+        let mut viewport = Grid::empty_with_size(40, 40);
+        universe.render_into(&mut viewport, origin);
+
+        // the soup can't reach the edges of the grid in 8 generations
+        for _ in 0..8 {
+            grid.next_gen();
+            universe.next_gen();
+            universe.render_into(&mut viewport, origin);
+            assert!(grid
+                .iter()
+                .zip(viewport.iter())
+                .all(|(g, v)| g.alive == v.alive && g.just_changed == v.just_changed));
+        }
+    }
+
+    #[test]
+    fn viewport() {
+        let mut universe = glider();
+        let mut viewport = Grid::empty_with_size(3, 3);
+
+        universe.render_into(&mut viewport, Position::new(-1, -1));
+        assert_eq!(viewport.count_ones(), 1);
+        assert!(
+            viewport
+                .get_cell(Index::new(1, 2).to_offset(3))
+                .unwrap()
+                .alive
+        );
+
+        universe.render_into(&mut viewport, Position::new(0, 0));
+        assert_eq!(viewport.count_ones(), 5);
+
+        for _ in 0..4 {
+            universe.next_gen();
+        }
+        // following the glider, the viewport doesn't change at all
+        universe.render_into(&mut viewport, Position::new(1, 1));
+        assert_eq!(viewport.count_ones(), 5);
+        assert!(!viewport.iter().any(|cell| cell.just_changed));
+
+        universe.render_into(&mut viewport, Position::new(0, 0));
+        assert_eq!(viewport.count_ones(), 1);
+        assert!(viewport.iter().any(|cell| cell.just_changed));
+    }
+
+    #[test]
+    fn empty_universe() {
+        let mut universe = Universe::new(Rule::highlife()).unwrap();
+        assert_eq!(universe.bounding_box(), None);
+        universe.next_gen();
+        assert_eq!(universe.population(), 0);
+    }
+
+    #[test]
+    fn rejects_birth_from_nothing() {
+        match Universe::new("B0/S8".parse().unwrap()) {
+            Err(GameError::UnsupportedRule(rule)) => assert_eq!(rule, "B0/S8"),
+            _ => panic!("B0 rule shouldn't be accepted"),
+        }
+        assert!(Universe::new(Rule::star_wars()).is_err());
+
+        let mut universe = glider();
+        assert!(universe.set_rule(Rule::star_wars()).is_err());
+        assert_eq!(universe.rule(), Rule::conway());
+        universe.set_rule(Rule::highlife()).unwrap();
+        assert_eq!(universe.rule(), Rule::highlife());
+    }
+}
@@ -1,268 +1,472 @@
-use crate::conway::{SubgridValuesIter, NUMBER_OF_SUBGRIDS};
-use crate::soundgen::{Result as SoundResult, SoundGenerator};
-
-pub trait SoundPlayer {
-    fn play_sounds(
-        pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()>;
+//! Strategies of playing the values of the subgrids of all the grids one after another. The
+//! strategies only compute a schedule of the notes, it is played later by the `SoundGenerator`.
+
+use crate::conway::{Conway, GenerationDelta, GridId, SubgridValuesIter, NUMBER_OF_SUBGRIDS};
+use crate::soundgen::pan_from_column;
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// Note of a single subgrid, `time` seconds after the start of the schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScheduledNote {
+    pub time: f64,
+    /// Index of the grid the note comes from
+    pub grid: usize,
+    /// Pitch value of the subgrid
+    pub pitch: u32,
+    /// Volume value of the subgrid
+    pub volume: u32,
 }
 
-/// Plays sound in cascading pattern:
-/// 1 1   1    1      1         1
-///     2   2    2      2         2          2
-///           3    3      3         3          3
-///                  4      4         4          4
-///                           5         5          5
-///                                       6          7
-/// =====================================================>
-/// etc.
-pub struct OverlappingCascadePlayer;
-
-/// Plays sounds in reverse cascading pattern
-/// 6 6   6    6      6         6
-///     5   5    5      5         5          5
-///           4    4      4         4          4
-///                  3      3         3          3
-///                           2         2          2
-///                                       1          1
-/// =====================================================>
-/// etc.
-pub struct ReverseOverlappingCascadePlayer;
-
-/// Plays sounds in linear order:
-/// 1 1 1 1 1 1 1 1
-///                 2 2 2 2 2 2 2 2 2 2
-///                                     3 3 3 3 3 3 3 3 3
-/// =====================================================>
-/// etc.
-pub struct LinearPlayer;
-
-/// Plays the sounds in reverse linear order
-/// 6 6 6 6 6 6 6 6
-///                 5 5 5 5 5 5 5 5 5 5
-///                                     4 4 4 4 4 4 4 4 4
-/// =====================================================>
-/// etc.
-pub struct ReverseLinearPlayer;
-
-/// Plays sounds in ping-pong order:
-/// 1                 1 1                 1
-///   2             2     2             2
-///     3         3         3         3
-///       4     4             4     4
-///         5 5                 5 5
-/// ====================================================>
-/// etc.
-pub struct PingPongPlayer;
-
-/// Plays sounds in ping-pong order:
-/// 5                 5 5                 1
-///   4             4     4             4
-///     3         3         3         3
-///       2     2             2     2
-///         1 1                 1 1
-/// ====================================================>
-/// etc.
-pub struct ReversePingPongPlayer;
-
-/// Plays sounds in cascading order:
-/// 1         1         1
-///   2         2         2
-///     3         3         3
-///       4         4         4
-///         5         5         5
-/// ====================================================>
-/// etc.
-pub struct CascadePlayer;
-
-/// Plays sounds in cascading order:
-/// 5         5         5
-///   4         4         4
-///     3         3         3
-///       2         2         2
-///         1         1         1
-/// ====================================================>
-/// etc.
-pub struct ReverseCascadePlayer;
-
-impl SoundPlayer for OverlappingCascadePlayer {
-    fn play_sounds(
-        mut pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()> {
-        let number_of_grids = pitches_and_volumes.len();
-        let mut finished_grids_counter: usize = 0;
-
-        for i in 0..number_of_grids * NUMBER_OF_SUBGRIDS {
-            let upper_bound: usize = (i + 1).min(number_of_grids);
-            for grid_idx in finished_grids_counter..upper_bound {
-                match pitches_and_volumes[grid_idx].next() {
-                    Some(&(pitch, _)) => {
-                        generator.play(pitch)?;
-                        sleep();
-                    }
-                    None => {
-                        finished_grids_counter += 1;
-                    }
-                }
-            }
+/// Notes played one after another, `step` seconds apart
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    step: f64,
+    notes: Vec<ScheduledNote>,
+}
+
+impl Schedule {
+    pub fn new(step: f64) -> Self {
+        Self {
+            step,
+            notes: Vec::new(),
         }
+    }
 
-        Ok(())
+    /// Appends a note one step after the previous one
+    pub fn push(&mut self, grid: usize, pitch: u32, volume: u32) {
+        let time = self.notes.len() as f64 * self.step;
+        self.notes.push(ScheduledNote {
+            time,
+            grid,
+            pitch,
+            volume,
+        });
+    }
+
+    pub fn notes(&self) -> &[ScheduledNote] {
+        &self.notes
+    }
+
+    pub fn len(&self) -> usize {
+        self.notes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// Time between the start of the schedule and the start of its last note
+    pub fn duration(&self) -> f64 {
+        self.notes.last().map_or(0.0, |note| note.time)
     }
 }
 
-impl SoundPlayer for LinearPlayer {
-    fn play_sounds(
-        mut pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()> {
-        pitches_and_volumes
-            .iter_mut()
-            .flatten()
-            .map(|&(pitch, _)| {
-                generator.play(pitch)?;
-                sleep();
-                Ok(())
-            })
-            .collect::<SoundResult<Vec<_>>>()?;
-        Ok(())
+/// Orders in which the subgrids of all the grids are played. The diagrams show the grids being
+/// played over time, each grid has as many notes as it has subgrids.
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, EnumString, Display)]
+pub enum Strategy {
+    /// Plays the grids one after another:
+    /// 1 1 1 1 1 1 1 1
+    ///                 2 2 2 2 2 2 2 2
+    ///                                 3 3 3 3 3 3 3 3
+    /// ===============================================>
+    Linear,
+    /// Plays the grids one after another, starting from the last one:
+    /// 3 3 3 3 3 3 3 3
+    ///                 2 2 2 2 2 2 2 2
+    ///                                 1 1 1 1 1 1 1 1
+    /// ===============================================>
+    ReverseLinear,
+    /// Plays a subgrid of each grid in turns:
+    /// 1     1     1
+    ///   2     2     2
+    ///     3     3     3
+    /// ===============>
+    Cascade,
+    /// Plays a subgrid of each grid in turns, starting from the last grid:
+    /// 3     3     3
+    ///   2     2     2
+    ///     1     1     1
+    /// ===============>
+    ReverseCascade,
+    /// Starts the grids one after another, every started grid plays a subgrid in each turn:
+    /// 1 1   1     1
+    ///     2   2     2     2
+    ///           3     3     3   3
+    /// ===========================>
+    OverlappingCascade,
+    /// Like `OverlappingCascade`, starting from the last grid:
+    /// 3 3   3     3
+    ///     2   2     2     2
+    ///           1     1     1   1
+    /// ===========================>
+    ReverseOverlappingCascade,
+    /// Plays a subgrid of each grid in turns, changing direction after every turn:
+    /// 1         1 1         1
+    ///   2     2     2     2
+    ///     3 3         3 3
+    /// =======================>
+    PingPong,
+    /// Like `PingPong`, starting from the last grid:
+    /// 3         3 3         3
+    ///   2     2     2     2
+    ///     1 1         1 1
+    /// =======================>
+    ReversePingPong,
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Strategy::Linear
     }
 }
 
-impl SoundPlayer for ReverseOverlappingCascadePlayer {
-    fn play_sounds(
-        mut pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()> {
-        let number_of_grids = pitches_and_volumes.len();
-        let mut finished_grids_counter: usize = 0;
-
-        for i in 0..number_of_grids * NUMBER_OF_SUBGRIDS {
-            let upper_bound: usize = (i + 1).min(number_of_grids);
-            for grid_idx in finished_grids_counter..upper_bound {
-                match pitches_and_volumes[number_of_grids - grid_idx].next() {
-                    Some(&(pitch, _)) => {
-                        generator.play(pitch)?;
-                        sleep();
-                    }
-                    None => {
-                        finished_grids_counter += 1;
-                    }
-                }
-            }
+impl Strategy {
+    /// Returns iterator over the (grid, subgrid) coordinates of the given number of grids with
+    /// the given number of subgrids each, in the order of the strategy
+    pub fn order(self, grids: usize, subgrids: usize) -> Order {
+        Order {
+            strategy: self,
+            grids,
+            subgrids,
+            position: 0,
         }
+    }
 
-        Ok(())
+    /// Orders the values of the subgrids into a schedule, with `step` seconds between the notes.
+    /// Grids with less subgrids than the others simply skip the missing ones.
+    pub fn schedule(self, pitches_and_volumes: Vec<SubgridValuesIter>, step: f64) -> Schedule {
+        let values: Vec<&[(u32, u32)]> = pitches_and_volumes
+            .iter()
+            .map(|values| values.as_slice())
+            .collect();
+        let subgrids = values.iter().map(|values| values.len()).max().unwrap_or(0);
+
+        let mut schedule = Schedule::new(step);
+        for (grid, &(pitch, volume)) in self.order(values.len(), subgrids).values(&values) {
+            schedule.push(grid, pitch, volume);
+        }
+        schedule
     }
-}
 
-impl SoundPlayer for ReverseLinearPlayer {
-    fn play_sounds(
-        mut pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()> {
-        pitches_and_volumes
+    /// Schedules the subgrids of the `grids` of the simulation within a single beat of `beat`
+    /// seconds. Returns the schedule and the id and the pan of each of its grids, the grids are
+    /// panned towards the columns where most of their changes happened.
+    pub fn schedule_grids(
+        self,
+        simulation: &mut Conway,
+        grids: &[(GridId, Option<&GenerationDelta>)],
+        beat: f64,
+    ) -> (Schedule, Vec<(GridId, f32)>) {
+        let step = beat / (grids.len() * NUMBER_OF_SUBGRIDS).max(1) as f64;
+        let (pans, values): (Vec<(GridId, f32)>, Vec<SubgridValuesIter>) = simulation
             .iter_mut()
-            .flatten()
-            .rev()
-            .map(|&(pitch, _)| {
-                generator.play(pitch)?;
-                sleep();
-                Ok(())
+            .filter_map(|(id, grid)| {
+                let (_, delta) = grids.iter().find(|(grid_id, _)| *grid_id == id)?;
+                let width = grid.width();
+                let pan = delta
+                    .and_then(GenerationDelta::centroid)
+                    .map(|(_, col)| pan_from_column(col, width))
+                    .unwrap_or(0.0);
+                Some(((id, pan), grid.get_pitch_and_volume_per_subgrid()))
             })
-            .collect::<SoundResult<Vec<_>>>()?;
-        Ok(())
+            .unzip();
+
+        (self.schedule(values, step), pans)
     }
 }
 
-impl SoundPlayer for PingPongPlayer {
-    fn play_sounds(
-        mut pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()> {
-        let number_of_grids = pitches_and_volumes.len();
-        let mut direction: isize = 1;
-        let mut index: usize = 0;
-
-        for _ in 0..NUMBER_OF_SUBGRIDS {
-            for _ in 0..number_of_grids {
-                generator.play(
-                    pitches_and_volumes[index]
-                        .next()
-                        .expect("Thish souldn't panic")
-                        .0,
-                )?;
-                sleep();
-                index = (index as isize + direction) as usize;
+/// Iterator over the (grid, subgrid) coordinates in the order of a strategy. Every coordinate is
+/// returned exactly once.
+#[derive(Debug, Clone)]
+pub struct Order {
+    strategy: Strategy,
+    grids: usize,
+    subgrids: usize,
+    position: usize,
+}
+
+impl Order {
+    /// Number of all the coordinates
+    fn total(&self) -> usize {
+        self.grids * self.subgrids
+    }
+
+    /// Coordinates at the `position` of the order
+    fn coordinates(&self, position: usize) -> (usize, usize) {
+        let (grids, subgrids) = (self.grids, self.subgrids);
+        let mirror = |grid: usize| grids - 1 - grid;
+        // the turn of the cascades and the position in it
+        let (turn, grid) = (position / grids, position % grids);
+
+        match self.strategy {
+            Strategy::Linear => (position / subgrids, position % subgrids),
+            Strategy::ReverseLinear => {
+                let position = self.total() - 1 - position;
+                (position / subgrids, position % subgrids)
+            }
+            Strategy::Cascade => (grid, turn),
+            Strategy::ReverseCascade => (mirror(grid), turn),
+            Strategy::OverlappingCascade => self.diagonal(position),
+            Strategy::ReverseOverlappingCascade => {
+                let (grid, subgrid) = self.diagonal(position);
+                (mirror(grid), subgrid)
             }
-            direction *= -1;
+            Strategy::PingPong if turn % 2 == 0 => (grid, turn),
+            Strategy::PingPong => (mirror(grid), turn),
+            Strategy::ReversePingPong if turn % 2 == 0 => (mirror(grid), turn),
+            Strategy::ReversePingPong => (grid, turn),
         }
-
-        Ok(())
     }
-}
 
-impl SoundPlayer for ReversePingPongPlayer {
-    fn play_sounds(
-        mut pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()> {
-        let number_of_grids = pitches_and_volumes.len();
-        let mut direction: isize = -1;
-        let mut index: usize = number_of_grids - 1;
-
-        for _ in 0..NUMBER_OF_SUBGRIDS {
-            for _ in 0..number_of_grids {
-                generator.play(
-                    pitches_and_volumes[index]
-                        .next()
-                        .expect("Thish souldn't panic")
-                        .0,
-                )?;
-                sleep();
-                index = (index as isize + direction) as usize;
+    /// Coordinates at the `position` of the overlapping cascade. The grid `g` plays its subgrid
+    /// `s` in the turn `g + s`, in each turn the grids are played in order.
+    fn diagonal(&self, mut position: usize) -> (usize, usize) {
+        for turn in 0usize.. {
+            let first = (turn + 1).saturating_sub(self.subgrids);
+            let last = turn.min(self.grids - 1);
+            let length = last + 1 - first;
+            if position < length {
+                let grid = first + position;
+                return (grid, turn - grid);
             }
-            direction *= -1;
+            position -= length;
         }
+        unreachable!("the position is within the order")
+    }
 
-        Ok(())
+    /// Adapts the order to return the values at the coordinates, with the index of their grid.
+    /// `values[grid][subgrid]` is the value of the subgrid, the missing values are skipped.
+    pub fn values<'v, T>(self, values: &'v [&'v [T]]) -> impl Iterator<Item = (usize, &'v T)> + 'v {
+        self.filter_map(move |(grid, subgrid)| {
+            let value = values.get(grid)?.get(subgrid)?;
+            Some((grid, value))
+        })
     }
 }
 
-impl SoundPlayer for CascadePlayer {
-    fn play_sounds(
-        mut pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()> {
-        let number_of_grids = pitches_and_volumes.len();
-        for _ in 0..NUMBER_OF_SUBGRIDS {
-            pitches_and_volumes.iter_mut().for_each(|mut iter| {
-                generator.play(iter.next().expect("This shouldn't panic").0);
-                sleep();
-            })
+impl Iterator for Order {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.total() {
+            return None;
         }
+        let coordinates = self.coordinates(self.position);
+        self.position += 1;
+        Some(coordinates)
+    }
 
-        Ok(())
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.total() - self.position;
+        (remaining, Some(remaining))
     }
 }
 
-impl SoundPlayer for ReverseCascadePlayer {
-    fn play_sounds(
-        mut pitches_and_volumes: Vec<SubgridValuesIter>,
-        generator: &SoundGenerator,
-    ) -> SoundResult<()> {
-        let number_of_grids = pitches_and_volumes.len();
-        for _ in 0..NUMBER_OF_SUBGRIDS {
-            pitches_and_volumes.iter_mut().rev().for_each(|mut iter| {
-                generator.play(iter.next().expect("This shouldn't panic").0);
-                sleep();
-            })
+impl ExactSizeIterator for Order {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conway::{Grid, Index};
+    use strum::IntoEnumIterator;
+
+    /// Largest number of grids and subgrids the properties are checked for
+    const MAX_SIZE: usize = 12;
+
+    const VALUES: [[(u32, u32); NUMBER_OF_SUBGRIDS]; 3] = [[(1, 4)], [(2, 5)], [(3, 6)]];
+
+    fn values() -> Vec<SubgridValuesIter<'static>> {
+        VALUES.iter().map(|values| values.iter()).collect()
+    }
+
+    fn order(schedule: &Schedule) -> Vec<(usize, u32)> {
+        schedule
+            .notes()
+            .iter()
+            .map(|note| (note.grid, note.pitch))
+            .collect()
+    }
+
+    #[test]
+    fn notes_are_spaced_by_steps() {
+        let mut schedule = Schedule::new(0.25);
+        assert_eq!(schedule.duration(), 0.0);
+        schedule.push(0, 7, 1);
+        schedule.push(1, 3, 1);
+        schedule.push(0, 5, 1);
+
+        let times: Vec<f64> = schedule.notes().iter().map(|note| note.time).collect();
+        assert_eq!(times, vec![0.0, 0.25, 0.5]);
+        assert_eq!(schedule.duration(), 0.5);
+    }
+
+    #[test]
+    fn linear_and_cascade_orders() {
+        let forward = vec![(0, 1), (1, 2), (2, 3)];
+        let backward = vec![(2, 3), (1, 2), (0, 1)];
+        assert_eq!(order(&Strategy::Linear.schedule(values(), 0.1)), forward);
+        assert_eq!(order(&Strategy::Cascade.schedule(values(), 0.1)), forward);
+        assert_eq!(
+            order(&Strategy::OverlappingCascade.schedule(values(), 0.1)),
+            forward
+        );
+        assert_eq!(
+            order(&Strategy::ReverseLinear.schedule(values(), 0.1)),
+            backward
+        );
+        assert_eq!(
+            order(&Strategy::ReverseCascade.schedule(values(), 0.1)),
+            backward
+        );
+    }
+
+    #[test]
+    fn volumes_are_kept() {
+        let schedule = Strategy::ReversePingPong.schedule(values(), 0.1);
+        let notes: Vec<(u32, u32)> = schedule
+            .notes()
+            .iter()
+            .map(|note| (note.pitch, note.volume))
+            .collect();
+        assert_eq!(notes, vec![(3, 6), (2, 5), (1, 4)]);
+    }
+
+    #[test]
+    fn orders_of_three_grids() {
+        let orders = [
+            (
+                Strategy::Linear,
+                vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)],
+            ),
+            (
+                Strategy::ReverseLinear,
+                vec![(2, 1), (2, 0), (1, 1), (1, 0), (0, 1), (0, 0)],
+            ),
+            (
+                Strategy::Cascade,
+                vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)],
+            ),
+            (
+                Strategy::ReverseCascade,
+                vec![(2, 0), (1, 0), (0, 0), (2, 1), (1, 1), (0, 1)],
+            ),
+            (
+                Strategy::OverlappingCascade,
+                vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 0), (2, 1)],
+            ),
+            (
+                Strategy::ReverseOverlappingCascade,
+                vec![(2, 0), (2, 1), (1, 0), (1, 1), (0, 0), (0, 1)],
+            ),
+            (
+                Strategy::PingPong,
+                vec![(0, 0), (1, 0), (2, 0), (2, 1), (1, 1), (0, 1)],
+            ),
+            (
+                Strategy::ReversePingPong,
+                vec![(2, 0), (1, 0), (0, 0), (0, 1), (1, 1), (2, 1)],
+            ),
+        ];
+
+        for (strategy, expected) in orders.iter() {
+            let order: Vec<_> = strategy.order(3, 2).collect();
+            assert_eq!(&order, expected, "{}", strategy);
         }
+    }
 
-        Ok(())
+    #[test]
+    fn overlapping_cascade_of_long_grids() {
+        let order: Vec<_> = Strategy::OverlappingCascade.order(2, 3).collect();
+        assert_eq!(order, vec![(0, 0), (0, 1), (1, 0), (0, 2), (1, 1), (1, 2)]);
     }
-}
 
-fn sleep() {}
+    #[test]
+    fn every_coordinate_exactly_once() {
+        for strategy in Strategy::iter() {
+            for grids in 0..=MAX_SIZE {
+                for subgrids in 0..=MAX_SIZE {
+                    let order = strategy.order(grids, subgrids);
+                    assert_eq!(order.len(), grids * subgrids);
+
+                    let mut coordinates: Vec<_> = order.collect();
+                    coordinates.sort_unstable();
+                    let expected: Vec<_> = (0..grids)
+                        .flat_map(|grid| (0..subgrids).map(move |subgrid| (grid, subgrid)))
+                        .collect();
+                    assert_eq!(coordinates, expected, "{} {}x{}", strategy, grids, subgrids);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_value_scheduled_exactly_once() {
+        for strategy in Strategy::iter() {
+            for grids in 0..=MAX_SIZE {
+                for subgrids in 0..=MAX_SIZE {
+                    // unique values, the grids get shorter to leave some subgrids missing
+                    let values: Vec<Vec<(u32, u32)>> = (0..grids)
+                        .map(|grid| {
+                            let length = subgrids.saturating_sub(grid % 3);
+                            (0..length)
+                                .map(|subgrid| ((grid * MAX_SIZE + subgrid) as u32, 0))
+                                .collect()
+                        })
+                        .collect();
+                    let schedule =
+                        strategy.schedule(values.iter().map(|values| values.iter()).collect(), 1.0);
+
+                    let mut scheduled = order(&schedule);
+                    scheduled.sort_unstable();
+                    let mut expected: Vec<(usize, u32)> = values
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(grid, values)| {
+                            values.iter().map(move |&(pitch, _)| (grid, pitch))
+                        })
+                        .collect();
+                    expected.sort_unstable();
+                    assert_eq!(scheduled, expected, "{} {}x{}", strategy, grids, subgrids);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn schedule_of_grids() {
+        let mut simulation = Conway::new();
+        let ids: Vec<GridId> = ["Blinker", "Glider", "Block"]
+            .iter()
+            .map(|name| {
+                let mut grid = Grid::with_pattern(name, Index::new(10, 10)).unwrap();
+                grid.start();
+                simulation.add_game(grid)
+            })
+            .collect();
+        let deltas = simulation.next_gen();
+
+        // the patterns are in the left part of the grids, the block doesn't change at all
+        let grids: Vec<_> = deltas
+            .iter()
+            .map(|(id, delta)| (*id, Some(delta)))
+            .collect();
+        let (schedule, pans) = Strategy::Linear.schedule_grids(&mut simulation, &grids, 1.0);
+        assert_eq!(schedule.len(), 3 * NUMBER_OF_SUBGRIDS);
+        assert!((schedule.duration() - (1.0 - 1.0 / schedule.len() as f64)).abs() < 1e-9);
+        assert_eq!(pans.iter().map(|(id, _)| *id).collect::<Vec<_>>(), ids);
+        assert!(pans[0].1 < 0.0 && pans[1].1 < 0.0);
+        assert_eq!(pans[2].1, 0.0);
+
+        let (schedule, pans) =
+            Strategy::Linear.schedule_grids(&mut simulation, &[(ids[1], None)], 1.0);
+        assert_eq!(pans, vec![(ids[1], 0.0)]);
+        assert_eq!(schedule.len(), NUMBER_OF_SUBGRIDS);
+    }
+
+    #[test]
+    fn strategy_names() {
+        assert_eq!(Strategy::PingPong.to_string(), "PingPong");
+        assert_eq!("Cascade".parse::<Strategy>().unwrap(), Strategy::Cascade);
+    }
+}
@@ -0,0 +1,256 @@
+//! Recording of the notes into Standard MIDI Files, with one track for each grid.
+
+use super::Result;
+use std::io::Write;
+
+/// Resolution of the recorded files, in ticks per quarter note
+pub const TICKS_PER_BEAT: u16 = 480;
+
+/// A single note of a track, the times are counted in ticks from the start of the recording
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MidiNote {
+    pub tick: u32,
+    pub duration: u32,
+    pub key: u8,
+    /// Velocity of the note on event, between 1 and 127
+    pub velocity: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub name: String,
+    pub notes: Vec<MidiNote>,
+}
+
+/// Notes recorded for a format 1 Standard MIDI File. The first track of the file holds the tempo,
+/// the notes of each grid go to their own tracks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    bpm: u32,
+    tracks: Vec<Track>,
+}
+
+impl Recording {
+    pub fn new(bpm: u32) -> Self {
+        Self {
+            bpm: bpm.max(1),
+            tracks: Vec::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.iter().all(|track| track.notes.is_empty())
+    }
+
+    /// Adds the note to the track with the `name`, the track is created with its first note
+    pub fn record(&mut self, track: &str, note: MidiNote) {
+        let index = match self.tracks.iter().position(|t| t.name == track) {
+            Some(index) => index,
+            None => {
+                self.tracks.push(Track {
+                    name: track.to_string(),
+                    notes: Vec::new(),
+                });
+                self.tracks.len() - 1
+            }
+        };
+        self.tracks[index].notes.push(note);
+    }
+
+    /// Writes the recording as a Standard MIDI File
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(b"MThd")?;
+        writer.write_all(&6u32.to_be_bytes())?;
+        // format 1, the tempo track and the tracks of the grids
+        writer.write_all(&1u16.to_be_bytes())?;
+        writer.write_all(&(self.tracks.len() as u16 + 1).to_be_bytes())?;
+        writer.write_all(&TICKS_PER_BEAT.to_be_bytes())?;
+
+        let microseconds_per_beat = 60_000_000 / self.bpm;
+        let mut tempo = vec![0x00, 0xff, 0x51, 0x03];
+        tempo.extend_from_slice(&microseconds_per_beat.to_be_bytes()[1..]);
+        write_chunk(writer, &end_track(tempo))?;
+
+        for track in &self.tracks {
+            write_chunk(writer, &end_track(track_events(track)))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_chunk<W: Write>(writer: &mut W, events: &[u8]) -> Result<()> {
+    writer.write_all(b"MTrk")?;
+    writer.write_all(&(events.len() as u32).to_be_bytes())?;
+    writer.write_all(events)?;
+    Ok(())
+}
+
+fn end_track(mut events: Vec<u8>) -> Vec<u8> {
+    events.extend_from_slice(&[0x00, 0xff, 0x2f, 0x00]);
+    events
+}
+
+/// Events of the track, starting with its name. The notes are played on the first channel, the
+/// notes ending at a tick are released before the notes starting at it. A key can't sound twice
+/// at once, so its notes end where its next note starts and only the first of the notes starting
+/// together is played.
+fn track_events(track: &Track) -> Vec<u8> {
+    let mut events = vec![0x00, 0xff, 0x03];
+    push_variable_length(&mut events, track.name.len() as u32);
+    events.extend_from_slice(track.name.as_bytes());
+
+    let mut notes: Vec<&MidiNote> = track.notes.iter().collect();
+    notes.sort_by_key(|note| (note.key.min(127), note.tick));
+    notes.dedup_by_key(|note| (note.key.min(127), note.tick));
+
+    // (tick, whether it is a note on, key, velocity)
+    let mut messages: Vec<(u32, bool, u8, u8)> = Vec::with_capacity(notes.len() * 2);
+    for (index, note) in notes.iter().enumerate() {
+        let key = note.key.min(127);
+        let mut end = note.tick + note.duration.max(1);
+        if let Some(next) = notes.get(index + 1).filter(|next| next.key.min(127) == key) {
+            end = end.min(next.tick);
+        }
+        messages.push((note.tick, true, key, note.velocity.max(1).min(127)));
+        messages.push((end, false, key, 0));
+    }
+    messages.sort_by_key(|&(tick, on, _, _)| (tick, on));
+
+    let mut last_tick = 0;
+    for (tick, on, key, velocity) in messages {
+        push_variable_length(&mut events, tick - last_tick);
+        last_tick = tick;
+        let status = if on { 0x90 } else { 0x80 };
+        events.extend_from_slice(&[status, key, velocity]);
+    }
+
+    events
+}
+
+/// Appends the value as a variable-length quantity, seven bits in each byte
+fn push_variable_length(bytes: &mut Vec<u8>, mut value: u32) {
+    let mut buffer = vec![(value & 0x7f) as u8];
+    value >>= 7;
+    while value > 0 {
+        buffer.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.extend(buffer.iter().rev());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn variable_length(value: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        push_variable_length(&mut bytes, value);
+        bytes
+    }
+
+    #[test]
+    fn variable_length_quantities() {
+        assert_eq!(variable_length(0), vec![0x00]);
+        assert_eq!(variable_length(0x7f), vec![0x7f]);
+        assert_eq!(variable_length(0x80), vec![0x81, 0x00]);
+        assert_eq!(variable_length(0x3fff), vec![0xff, 0x7f]);
+        assert_eq!(variable_length(0x0fff_ffff), vec![0xff, 0xff, 0xff, 0x7f]);
+    }
+
+    #[test]
+    fn tracks_by_name() {
+        let mut recording = Recording::new(120);
+        assert!(recording.is_empty());
+        let note = MidiNote {
+            tick: 0,
+            duration: 10,
+            key: 60,
+            velocity: 100,
+        };
+        recording.record("a", note);
+        recording.record("b", note);
+        recording.record("a", MidiNote { tick: 20, ..note });
+
+        let names: Vec<&str> = recording.tracks().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(recording.tracks()[0].notes.len(), 2);
+        assert!(!recording.is_empty());
+    }
+
+    #[test]
+    fn standard_midi_file() {
+        let mut recording = Recording::new(120);
+        let note = MidiNote {
+            tick: 0,
+            duration: 480,
+            key: 60,
+            velocity: 100,
+        };
+        recording.record("grid", note);
+        recording.record("grid", MidiNote { tick: 480, ..note });
+
+        let mut file = Vec::new();
+        recording.write(&mut file).unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(b"MThd");
+        expected.extend_from_slice(&[0, 0, 0, 6, 0, 1, 0, 2, 0x01, 0xe0]);
+        // the tempo of 500000 microseconds per beat
+        expected.extend_from_slice(b"MTrk");
+        expected.extend_from_slice(&[0, 0, 0, 11]);
+        expected.extend_from_slice(&[0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20]);
+        expected.extend_from_slice(&[0x00, 0xff, 0x2f, 0x00]);
+        expected.extend_from_slice(b"MTrk");
+        expected.extend_from_slice(&[0, 0, 0, 30]);
+        expected.extend_from_slice(&[0x00, 0xff, 0x03, 0x04]);
+        expected.extend_from_slice(b"grid");
+        expected.extend_from_slice(&[0x00, 0x90, 60, 100]);
+        // the first note ends before the second one starts
+        expected.extend_from_slice(&[0x83, 0x60, 0x80, 60, 0]);
+        expected.extend_from_slice(&[0x00, 0x90, 60, 100]);
+        expected.extend_from_slice(&[0x83, 0x60, 0x80, 60, 0]);
+        expected.extend_from_slice(&[0x00, 0xff, 0x2f, 0x00]);
+
+        assert_eq!(file, expected);
+    }
+
+    #[test]
+    fn repeated_keys() {
+        let note = MidiNote {
+            tick: 0,
+            duration: 960,
+            key: 60,
+            velocity: 100,
+        };
+        let track = Track {
+            name: String::new(),
+            notes: vec![
+                MidiNote { tick: 480, ..note },
+                note,
+                MidiNote {
+                    tick: 480,
+                    velocity: 50,
+                    ..note
+                },
+                MidiNote { key: 64, ..note },
+            ],
+        };
+
+        let mut expected = vec![0x00, 0xff, 0x03, 0x00];
+        expected.extend_from_slice(&[0x00, 0x90, 60, 100]);
+        expected.extend_from_slice(&[0x00, 0x90, 64, 100]);
+        // the first note of the key is cut short by the second one
+        expected.extend_from_slice(&[0x83, 0x60, 0x80, 60, 0]);
+        expected.extend_from_slice(&[0x00, 0x90, 60, 100]);
+        expected.extend_from_slice(&[0x83, 0x60, 0x80, 64, 0]);
+        expected.extend_from_slice(&[0x83, 0x60, 0x80, 60, 0]);
+
+        assert_eq!(track_events(&track), expected);
+    }
+}
@@ -1,97 +1,224 @@
 //! A simple utility to deterministically generate and play consonant sounds based on
 //! some input value.
 
+mod midi;
+mod render;
+mod scale;
+mod tuning;
+mod wav;
+mod web;
+
+pub use midi::{MidiNote, Recording, TICKS_PER_BEAT};
+pub use render::{render_session, Renderer};
+pub use scale::{Note, Scale, ScaleKind};
+pub use tuning::{Tuning, STANDARD_PITCH};
+pub use wav::{write_wav, SampleFormat};
+pub use web::WebAudio;
+
+use crate::player::Schedule;
+use std::convert::TryFrom;
 use thiserror::Error;
 use wasm_bindgen::JsValue;
-use web_sys::{AudioContext, OscillatorType};
 
 pub type Result<V> = std::result::Result<V, SoundError>;
 
-pub struct SoundGenerator {
-    ctx: AudioContext,
+/// Volume value at which the notes reach the full velocity
+pub const FULL_VOLUME: u32 = 64;
+
+/// A single note to be played
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteEvent {
+    /// Value the note is generated from
+    pub pitch: u32,
+    /// Loudness of the note, from 0 for silence to 1 for the peak of the sound
+    pub velocity: f32,
+    /// Seconds from the start of the note to its end
+    pub duration: f64,
+}
+
+/// Maps volume values onto velocities
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VelocityCurve {
+    /// Velocity grows in proportion to the volume
+    Linear,
+    /// Velocity grows quickly for the low volumes and slowly for the high ones
+    Log,
+    /// Linear velocity kept between `min` and `max`, so the quiet notes can still be heard
+    Clamped { min: f32, max: f32 },
+}
+
+impl VelocityCurve {
+    /// Returns velocity between 0 and 1 for the `volume`, the volumes of `full` and more play at
+    /// the full velocity of the curve
+    pub fn velocity(self, volume: u32, full: u32) -> f32 {
+        let full = full.max(1);
+        let linear = volume.min(full) as f32 / full as f32;
+        match self {
+            VelocityCurve::Linear => linear,
+            VelocityCurve::Log => (volume.min(full) as f32).ln_1p() / (full as f32).ln_1p(),
+            VelocityCurve::Clamped { min, max } => {
+                let (min, max) = (min.max(0.0).min(1.0), max.max(0.0).min(1.0));
+                linear.max(min.min(max)).min(max)
+            }
+        }
+    }
+}
+
+impl Default for VelocityCurve {
+    fn default() -> Self {
+        VelocityCurve::Clamped { min: 0.2, max: 1.0 }
+    }
+}
+
+/// Waveform of the oscillators
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Sine,
+    Square,
+    Sawtooth,
+    Triangle,
+}
+
+/// A note with everything needed to synthesize it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Voice {
+    pub frequency: f32,
+    /// Highest gain of the envelope
+    pub peak: f32,
+    /// Seconds from now to the start of the note
+    pub offset: f64,
+    pub duration: f64,
+    /// Place in the stereo field, -1 is fully left and 1 is fully right
+    pub pan: f32,
+}
+
+/// Something that can make the sound of voices, be it the speakers or a file
+pub trait SynthBackend {
+    fn play(&mut self, sound: &Sound, voice: &Voice) -> Result<()>;
+}
+
+pub struct SoundGenerator<B: SynthBackend = WebAudio> {
+    backend: B,
     sound: Sound,
     notegen: NoteGenerator,
+    velocity_curve: VelocityCurve,
 }
 
 impl SoundGenerator {
+    /// Creates generator playing the sounds in the browser
     pub fn new() -> SoundGenerator {
+        SoundGenerator::with_backend(WebAudio::new().unwrap())
+    }
+}
+
+impl<B: SynthBackend> SoundGenerator<B> {
+    pub fn with_backend(backend: B) -> Self {
         SoundGenerator {
-            ctx: AudioContext::new().unwrap(),
+            backend,
             sound: Sound::staccato_sine(),
             notegen: NoteGenerator::new(Range::new(3, 7), Scale::pentatonic()),
+            velocity_curve: VelocityCurve::default(),
         }
     }
 
-    #[allow(dead_code)]
-    pub fn set_sound(&mut self, sound: Sound) {
-        self.sound = sound;
+    pub fn backend(&self) -> &B {
+        &self.backend
     }
 
-    #[allow(dead_code)]
-    pub fn set_range(&mut self, range: Range) {
-        self.notegen.range = range;
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
     }
 
-    #[allow(dead_code)]
     pub fn set_scale(&mut self, scale: Scale) {
         self.notegen.scale = scale;
     }
 
-    pub fn play(&self, value: u32) -> Result<()> {
-        let sound = &self.sound;
-
-        // Create our web audio objects.
-        let primary = self.ctx.create_oscillator()?;
-        let fm_osc = self.ctx.create_oscillator()?;
-        let gain = self.ctx.create_gain()?;
-        let fm_gain = self.ctx.create_gain()?;
-
-        // Some initial settings:
-        primary.set_type(sound.oscillator_type);
-        primary
-            .frequency()
-            .set_value(self.notegen.frequency_from_value(value));
-
-        // Give the amp a shape.
-        gain.gain().set_value(0.0);
-        gain.gain()
-            .linear_ramp_to_value_at_time(sound.peak, self.ctx.current_time() + sound.attack)?;
-        gain.gain().linear_ramp_to_value_at_time(
-            0.0,
-            self.ctx.current_time() + sound.sweep - sound.release,
-        )?;
+    pub fn set_tuning(&mut self, tuning: Tuning) {
+        self.notegen.tuning = tuning;
+    }
 
-        // The FM oscillator isn't really used right now.
-        fm_gain.gain().set_value(sound.fm_gain); // no initial frequency modulation
-        fm_osc.set_type(OscillatorType::Sine);
-        fm_osc.frequency().set_value(sound.fm_freq);
+    pub fn set_velocity_curve(&mut self, curve: VelocityCurve) {
+        self.velocity_curve = curve;
+    }
 
-        // Connect the nodes up!
+    /// Returns the note of the given pitch and volume values, lasting for the whole sound
+    pub fn note(&self, pitch: u32, volume: u32) -> NoteEvent {
+        NoteEvent {
+            pitch,
+            velocity: self.velocity_curve.velocity(volume, FULL_VOLUME),
+            duration: self.sound.sweep,
+        }
+    }
 
-        // The primary oscillator is routed through the gain node, so that
-        // it can control the overall output volume.
-        primary.connect_with_audio_node(&gain)?;
+    pub fn play(&mut self, note: NoteEvent) -> Result<()> {
+        self.play_panned(note, 0.0)
+    }
 
-        // Then connect the gain node to the AudioContext destination (aka
-        // your speakers).
-        gain.connect_with_audio_node(&self.ctx.destination())?;
+    /// Plays the note placed in the stereo field, -1 is fully left and 1 is fully right
+    pub fn play_panned(&mut self, note: NoteEvent, pan: f32) -> Result<()> {
+        self.play_at(note, pan, 0.0)
+    }
 
-        // The FM oscillator is connected to its own gain node, so it can
-        // control the amount of modulation.
-        fm_osc.connect_with_audio_node(&fm_gain)?;
+    /// Plays all the notes of the schedule, starting now. `pan` places the notes of each grid in
+    /// the stereo field.
+    pub fn play_schedule(&mut self, schedule: &Schedule, pan: impl Fn(usize) -> f32) -> Result<()> {
+        for scheduled in schedule.notes() {
+            let note = self.note(scheduled.pitch, scheduled.volume);
+            self.play_at(note, pan(scheduled.grid), scheduled.time)?;
+        }
+        Ok(())
+    }
 
-        // Connect the FM oscillator to the frequency parameter of the main
-        // oscillator, so that the FM node can modulate its frequency.
-        fm_gain.connect_with_audio_param(&primary.frequency())?;
+    /// Records the notes of the schedule played at the `start` beat of the recording, with beats
+    /// lasting for `beat` seconds. `track` names the track of the notes of each grid.
+    pub fn record_schedule(
+        &self,
+        recording: &mut Recording,
+        schedule: &Schedule,
+        start: u64,
+        beat: f64,
+        track: impl Fn(usize) -> String,
+    ) {
+        let ticks = |seconds: f64| (seconds / beat * TICKS_PER_BEAT as f64).round() as u32;
+        let start = start.saturating_mul(u64::from(TICKS_PER_BEAT));
+        for scheduled in schedule.notes() {
+            // the ticks of the MIDI files don't go any further
+            let tick = match u32::try_from(start.saturating_add(u64::from(ticks(scheduled.time)))) {
+                Ok(tick) => tick,
+                Err(_) => continue,
+            };
+            let note = self.note(scheduled.pitch, scheduled.volume);
+            let midi_note = MidiNote {
+                tick,
+                duration: ticks(note.duration).max(1),
+                key: self.notegen.midi_note_from_value(note.pitch),
+                velocity: (note.velocity * 127.0).round().max(1.0).min(127.0) as u8,
+            };
+            recording.record(&track(scheduled.grid), midi_note);
+        }
+    }
 
-        // Start the oscillators!
-        primary.start()?;
-        primary.stop_with_when(self.ctx.current_time() + sound.sweep)?;
-        fm_osc.start()?;
-        fm_osc.stop_with_when(self.ctx.current_time() + sound.sweep)?;
+    /// Plays the note `offset` seconds from now. The louder the note the higher the peak of its
+    /// envelope.
+    pub fn play_at(&mut self, note: NoteEvent, pan: f32, offset: f64) -> Result<()> {
+        let voice = Voice {
+            frequency: self.notegen.frequency_from_value(note.pitch),
+            peak: self.sound.peak * note.velocity.max(0.0).min(1.0),
+            offset,
+            duration: note.duration,
+            pan,
+        };
+        self.backend.play(&self.sound, &voice)
+    }
+}
 
-        Ok(())
+/// Maps a column of a grid of the given width onto the stereo field, the leftmost column is
+/// fully left and the rightmost one fully right
+pub fn pan_from_column(col: f64, width: usize) -> f32 {
+    if width < 2 {
+        return 0.0;
     }
+    (col / (width - 1) as f64 * 2.0 - 1.0) as f32
 }
 
 pub struct Sound {
@@ -99,7 +226,8 @@ pub struct Sound {
     release: f64,
     sweep: f64,
     peak: f32,
-    oscillator_type: OscillatorType,
+    waveform: Waveform,
+    /// Depth of the frequency modulation, in Hz
     fm_gain: f32,
     fm_freq: f32,
 }
@@ -112,49 +240,90 @@ impl Sound {
             release: 0.3,
             sweep: 0.7,
             peak: 0.3,
-            oscillator_type: OscillatorType::Sine,
+            waveform: Waveform::Sine,
             fm_gain: 0.0,
             fm_freq: 0.0,
         }
     }
+
+    /// Whether a note lasting for `duration` has room for the whole attack and release
+    fn fits(&self, duration: f64) -> bool {
+        self.attack + self.release <= duration
+    }
+
+    /// Seconds from the start of a note lasting for `duration` to the peak of its envelope. The
+    /// attack of the shorter notes takes the same share of their duration as of the sound.
+    fn attack_end(&self, duration: f64) -> f64 {
+        if self.fits(duration) {
+            self.attack
+        } else {
+            duration.max(0.0) * self.attack / (self.attack + self.release)
+        }
+    }
+
+    /// Seconds from the start of a note lasting for `duration` to the end of its release. The
+    /// envelope rises until the end of the attack and falls until the end of the release, the
+    /// shorter notes fall until their end.
+    fn release_start(&self, duration: f64) -> f64 {
+        if self.fits(duration) {
+            duration - self.release
+        } else {
+            duration.max(0.0)
+        }
+    }
 }
 
 pub struct NoteGenerator {
     range: Range,
     scale: Scale,
+    tuning: Tuning,
 }
 
 impl NoteGenerator {
     pub fn new(range: Range, scale: Scale) -> Self {
-        NoteGenerator { range, scale }
+        NoteGenerator {
+            range,
+            scale,
+            tuning: Tuning::default(),
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn set_range(&mut self, range: Range) {
-        self.range = range;
+    /// Given a u32 value, this will deterministically produce notes from this generator's scale
+    /// and range. The result is a midi note, the nearest one for the microtones.
+    pub fn midi_note_from_value(&self, value: u32) -> u8 {
+        if self.scale.in_degrees() {
+            return self.tuning.midi_note(self.frequency_from_value(value));
+        }
+        let (index, octave) = self.scale_note(value);
+        12 + self.scale.semitones(index) + (octave * 12) as u8
     }
 
-    #[allow(dead_code)]
-    pub fn set_scale(&mut self, scale: Scale) {
-        self.scale = scale;
+    /// Frequency of the note of the value in the tuning of this generator. The scales counted in
+    /// degrees play the degrees of the tuning, the others are rounded onto them.
+    pub fn frequency_from_value(&self, value: u32) -> f32 {
+        if self.scale.in_degrees() {
+            let (index, octave) = self.scale_note(value);
+            let degrees =
+                (octave + 1) * self.tuning.len() as u32 + self.scale.interval(index) as u32;
+            self.tuning
+                .degree_frequency(degrees as i32, self.scale.root())
+        } else {
+            self.tuning
+                .frequency(self.midi_note_from_value(value), self.scale.root())
+        }
     }
 
-    /// Given a u32 value, this will deterministically produce notes from this generator's scale
-    /// and range. The result is a midi note.
-    pub fn midi_note_from_value(&self, value: u32) -> u8 {
+    /// Index of the note of the value in the scale and the octave it is played in
+    fn scale_note(&self, value: u32) -> (usize, u32) {
         let divisor = self.scale.len();
 
-        // Choose a note from the scale.
-        let base = self.scale.0[value as usize % divisor] as u8;
-
         // Figure out how many octaves we have to move it up.
         let octave_shift = (value / divisor as u32) % self.range.count();
 
-        12 + base + ((self.range.lowest_octave as u32 + octave_shift) * 12) as u8
-    }
-
-    pub fn frequency_from_value(&self, value: u32) -> f32 {
-        midi_to_freq(self.midi_note_from_value(value))
+        (
+            value as usize % divisor,
+            self.range.lowest_octave as u32 + octave_shift,
+        )
     }
 }
 
@@ -177,46 +346,21 @@ impl Range {
     }
 }
 
-#[derive(Copy, Clone)]
-#[allow(dead_code)]
-enum Note {
-    C = 0,
-    Db = 1,
-    D = 2,
-    Eb = 3,
-    E = 4,
-    F = 5,
-    Gb = 6,
-    G = 7,
-    Ab = 8,
-    A = 9,
-    Bb = 10,
-    B = 11,
-}
-
-pub struct Scale(Vec<Note>);
-
-impl Scale {
-    pub fn pentatonic() -> Self {
-        use Note::*;
-
-        Scale(vec![C, D, E, G, A])
-    }
-
-    pub fn len(&self) -> usize {
-        self.0.len()
-    }
-}
-
-fn midi_to_freq(note: u8) -> f32 {
-    27.5 * 2f32.powf((note as f32 - 21.0) / 12.0)
-}
-
 // For how to use this, see: https://docs.rs/thiserror/1.0.20/thiserror/
 #[derive(Error, Debug)]
 pub enum SoundError {
     #[error("WASM error")]
     WasmError,
+    #[error("can't write the audio: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("unknown note: {0}")]
+    UnknownNote(String),
+    #[error("scale without notes")]
+    EmptyScale,
+    #[error("invalid Scala file: {0}")]
+    InvalidScala(String),
+    #[error("too much audio for a WAV file")]
+    TooLong,
 }
 
 impl From<JsValue> for SoundError {
@@ -251,3 +395,129 @@ fn note_generation_three_octave_pentatonic() {
     assert_eq!(notegen.midi_note_from_value(13), 67);
     assert_eq!(notegen.midi_note_from_value(18), 43);
 }
+
+#[test]
+fn note_generation_in_other_keys() {
+    let notegen = NoteGenerator::new(Range::new(1, 2), ScaleKind::Major.scale(Note::A));
+
+    // the notes of the root octave go above its C, the scale keeps ascending
+    assert_eq!(notegen.midi_note_from_value(0), 33);
+    assert_eq!(notegen.midi_note_from_value(2), 37);
+    assert_eq!(notegen.midi_note_from_value(6), 44);
+    assert_eq!(notegen.midi_note_from_value(7), 45);
+    assert_eq!(notegen.midi_note_from_value(14), 33);
+}
+
+#[test]
+fn note_generation_in_degrees() {
+    let mut notegen = NoteGenerator::new(Range::new(4, 5), "C 0 1 2".parse().unwrap());
+    notegen.tuning = Tuning::equal(24);
+
+    let c4 = notegen.frequency_from_value(0);
+    assert!((c4 - 261.63).abs() < 0.01);
+    // the quarter tone above the middle C
+    assert!((notegen.frequency_from_value(1) / c4 - 2f32.powf(1.0 / 24.0)).abs() < 0.0001);
+    assert!((notegen.frequency_from_value(3) / c4 - 2.0).abs() < 0.0001);
+    assert_eq!(notegen.midi_note_from_value(0), 60);
+    assert_eq!(notegen.midi_note_from_value(2), 61);
+    assert_eq!(notegen.midi_note_from_value(3), 72);
+}
+
+#[test]
+fn velocity_curves() {
+    let linear = VelocityCurve::Linear;
+    assert_eq!(linear.velocity(0, 64), 0.0);
+    assert_eq!(linear.velocity(16, 64), 0.25);
+    assert_eq!(linear.velocity(200, 64), 1.0);
+
+    let log = VelocityCurve::Log;
+    assert_eq!(log.velocity(0, 64), 0.0);
+    assert!(log.velocity(16, 64) > linear.velocity(16, 64));
+    assert_eq!(log.velocity(64, 64), 1.0);
+
+    let clamped = VelocityCurve::Clamped { min: 0.2, max: 0.8 };
+    assert_eq!(clamped.velocity(0, 64), 0.2);
+    assert_eq!(clamped.velocity(32, 64), 0.5);
+    assert_eq!(clamped.velocity(64, 64), 0.8);
+}
+
+#[test]
+fn louder_with_more_births() {
+    for curve in &[
+        VelocityCurve::Linear,
+        VelocityCurve::Log,
+        VelocityCurve::default(),
+    ] {
+        let velocities: Vec<f32> = (0..=FULL_VOLUME)
+            .map(|volume| curve.velocity(volume, FULL_VOLUME))
+            .collect();
+        assert!(velocities.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(velocities.iter().all(|v| (0.0..=1.0).contains(v)));
+    }
+    assert_eq!(VelocityCurve::Linear.velocity(5, 0), 1.0);
+}
+
+#[test]
+fn notes_recorded_too_late() {
+    let generator = SoundGenerator::with_backend(Renderer::default());
+    let mut schedule = Schedule::new(0.25);
+    schedule.push(0, 0, FULL_VOLUME);
+
+    let mut recording = Recording::new(120);
+    generator.record_schedule(&mut recording, &schedule, u64::MAX, 0.5, |_| "grid".into());
+    generator.record_schedule(&mut recording, &schedule, 1 << 24, 0.5, |_| "grid".into());
+    assert!(recording.is_empty());
+
+    generator.record_schedule(&mut recording, &schedule, 1 << 20, 0.5, |_| "grid".into());
+    assert_eq!(
+        recording.tracks()[0].notes[0].tick,
+        u32::from(TICKS_PER_BEAT) << 20
+    );
+}
+
+#[test]
+fn velocity_curve_of_the_notes() {
+    let mut generator = SoundGenerator::with_backend(Renderer::default());
+    assert_eq!(generator.note(0, 0).velocity, 0.2);
+
+    generator.set_velocity_curve(VelocityCurve::Linear);
+    assert_eq!(generator.note(0, 0).velocity, 0.0);
+    assert_eq!(generator.note(0, FULL_VOLUME / 4).velocity, 0.25);
+}
+
+#[test]
+fn recorded_schedule() {
+    let generator = SoundGenerator::with_backend(Renderer::default());
+    let mut schedule = Schedule::new(0.25);
+    schedule.push(0, 0, 0);
+    schedule.push(1, 1, FULL_VOLUME);
+
+    let mut recording = Recording::new(120);
+    generator.record_schedule(&mut recording, &schedule, 2, 0.5, |grid| {
+        format!("grid {}", grid)
+    });
+
+    let tracks = recording.tracks();
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[0].name, "grid 0");
+    // the staccato lasts for 0.7 seconds, 1.4 beats
+    assert_eq!(
+        tracks[0].notes,
+        vec![MidiNote {
+            tick: 960,
+            duration: 672,
+            key: generator.notegen.midi_note_from_value(0),
+            velocity: 25,
+        }]
+    );
+    assert_eq!(tracks[1].notes[0].tick, 1200);
+    assert_eq!(tracks[1].notes[0].velocity, 127);
+}
+
+#[test]
+fn pan_across_columns() {
+    assert_eq!(pan_from_column(0.0, 50), -1.0);
+    assert_eq!(pan_from_column(49.0, 50), 1.0);
+    assert_eq!(pan_from_column(2.0, 5), 0.0);
+    assert_eq!(pan_from_column(0.0, 1), 0.0);
+}
@@ -0,0 +1,324 @@
+//! Backend rendering the sounds into samples, so they can be written to files and checked
+//! without a browser.
+
+use super::{
+    write_wav, Result, SampleFormat, Sound, SoundGenerator, SynthBackend, Voice, Waveform,
+};
+use crate::conway::Conway;
+use crate::player::Strategy;
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::io::Write;
+
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// The rendered sounds are always stereo
+const CHANNELS: u16 = 2;
+
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    sample_rate: u32,
+    /// Seconds from the start of the rendering, the offsets of the voices are counted from it
+    now: f64,
+    /// Interleaved samples of the left and the right channel
+    samples: Vec<f32>,
+}
+
+impl Renderer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            now: 0.0,
+            samples: Vec::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    /// Moves the time forward, the rendering lasts at least until the new time
+    pub fn advance(&mut self, seconds: f64) {
+        self.now += seconds.max(0.0);
+        let frames = self.frame(self.now);
+        self.reserve(frames);
+    }
+
+    /// Interleaved samples of the left and the right channel
+    #[cfg(test)]
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn write_wav<W: Write>(&self, writer: &mut W, format: SampleFormat) -> Result<()> {
+        write_wav(writer, &self.samples, CHANNELS, self.sample_rate, format)
+    }
+
+    fn frame(&self, time: f64) -> usize {
+        (time * self.sample_rate as f64).round() as usize
+    }
+
+    /// Makes the samples long enough to hold the `frames`
+    fn reserve(&mut self, frames: usize) {
+        let length = frames * CHANNELS as usize;
+        if self.samples.len() < length {
+            self.samples.resize(length, 0.0);
+        }
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new(SAMPLE_RATE)
+    }
+}
+
+impl SynthBackend for Renderer {
+    fn play(&mut self, sound: &Sound, voice: &Voice) -> Result<()> {
+        let sample_rate = self.sample_rate as f64;
+        let start = self.frame(self.now + voice.offset.max(0.0));
+        let frames = self.frame(voice.duration.max(0.0));
+        self.reserve(start + frames);
+
+        // equal-power panning, like the stereo panner of the browsers
+        let angle = (voice.pan.max(-1.0).min(1.0) as f64 + 1.0) / 2.0 * FRAC_PI_2;
+        let (left, right) = (angle.cos(), angle.sin());
+
+        let mut phase = 0.0;
+        for frame in 0..frames {
+            let time = frame as f64 / sample_rate;
+            let value = oscillate(sound.waveform, phase) * envelope(sound, voice, time);
+            let at = (start + frame) * CHANNELS as usize;
+            self.samples[at] += (value * left) as f32;
+            self.samples[at + 1] += (value * right) as f32;
+
+            let modulation = sound.fm_gain as f64 * (2.0 * PI * sound.fm_freq as f64 * time).sin();
+            phase = (phase + (voice.frequency as f64 + modulation) / sample_rate).rem_euclid(1.0);
+        }
+
+        Ok(())
+    }
+}
+
+/// Value of the waveform at the `phase` of its period, between 0 and 1
+fn oscillate(waveform: Waveform, phase: f64) -> f64 {
+    match waveform {
+        Waveform::Sine => (2.0 * PI * phase).sin(),
+        Waveform::Square if phase < 0.5 => 1.0,
+        Waveform::Square => -1.0,
+        Waveform::Sawtooth => 2.0 * (phase + 0.5).fract() - 1.0,
+        Waveform::Triangle => 4.0 * ((phase + 0.75).fract() - 0.5).abs() - 1.0,
+    }
+}
+
+/// Gain of the voice `time` seconds after its start. It rises linearly to the peak during the
+/// attack and falls linearly to silence until the end of the release.
+fn envelope(sound: &Sound, voice: &Voice, time: f64) -> f64 {
+    let peak = voice.peak as f64;
+    let attack_end = sound.attack_end(voice.duration);
+    let release_start = sound.release_start(voice.duration);
+    if time < attack_end {
+        peak * time / attack_end
+    } else if time < release_start {
+        peak * (release_start - time) / (release_start - attack_end)
+    } else {
+        0.0
+    }
+}
+
+/// Plays `generations` generations of the grids of the simulation, one generation every `beat`
+/// seconds, the way the client does. Seeded grids always render into the same samples.
+pub fn render_session(
+    simulation: &mut Conway,
+    generations: usize,
+    strategy: Strategy,
+    beat: f64,
+    generator: &mut SoundGenerator<Renderer>,
+) -> Result<()> {
+    for _ in 0..generations {
+        let deltas = simulation.next_gen();
+        let grids: Vec<_> = deltas
+            .iter()
+            .map(|(id, delta)| (*id, Some(delta)))
+            .collect();
+        let (schedule, pans) = strategy.schedule_grids(simulation, &grids, beat);
+        generator.play_schedule(&schedule, |grid| pans[grid].1)?;
+        generator.backend_mut().advance(beat);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::conway::{Grid, DEFAULT_DENSITY};
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    fn sound(waveform: Waveform) -> Sound {
+        Sound {
+            attack: 0.0,
+            release: 0.0,
+            sweep: 1.0,
+            peak: 1.0,
+            waveform,
+            fm_gain: 0.0,
+            fm_freq: 0.0,
+        }
+    }
+
+    fn voice(frequency: f32) -> Voice {
+        Voice {
+            frequency,
+            peak: 1.0,
+            offset: 0.0,
+            duration: 1.0,
+            pan: 0.0,
+        }
+    }
+
+    fn left(renderer: &Renderer) -> Vec<f32> {
+        renderer.samples().iter().step_by(2).copied().collect()
+    }
+
+    fn rising_crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count()
+    }
+
+    #[test]
+    fn frequency_of_waveforms() {
+        for &waveform in &[
+            Waveform::Sine,
+            Waveform::Square,
+            Waveform::Sawtooth,
+            Waveform::Triangle,
+        ] {
+            let mut renderer = Renderer::default();
+            renderer.play(&sound(waveform), &voice(441.0)).unwrap();
+
+            let samples = left(&renderer);
+            assert_eq!(samples.len(), SAMPLE_RATE as usize);
+            let crossings = rising_crossings(&samples);
+            assert!(
+                (440..=441).contains(&crossings),
+                "{:?} {}",
+                waveform,
+                crossings
+            );
+            assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+        }
+    }
+
+    #[test]
+    fn envelope_and_offset() {
+        let mut renderer = Renderer::new(1000);
+        let sound = Sound {
+            attack: 0.1,
+            release: 0.2,
+            ..sound(Waveform::Square)
+        };
+        let voice = Voice {
+            offset: 0.5,
+            duration: 0.5,
+            pan: -1.0,
+            ..voice(1.0)
+        };
+        renderer.play(&sound, &voice).unwrap();
+
+        let samples = left(&renderer);
+        assert_eq!(samples.len(), 1000);
+        assert!(samples[..500].iter().all(|&sample| sample == 0.0));
+        // the peak is at the end of the attack, the release falls to silence
+        assert!((samples[600] - 1.0).abs() < 1e-6);
+        assert!((samples[650] - 0.75).abs() < 1e-6);
+        assert!(samples[800..].iter().all(|&sample| sample == 0.0));
+        // fully left
+        assert!(renderer.samples()[1..]
+            .iter()
+            .step_by(2)
+            .all(|s| s.abs() < 1e-6));
+    }
+
+    #[test]
+    fn notes_shorter_than_the_attack() {
+        let mut renderer = Renderer::new(1000);
+        let sound = Sound {
+            attack: 0.1,
+            release: 0.2,
+            ..sound(Waveform::Square)
+        };
+        let voice = Voice {
+            duration: 0.06,
+            ..voice(1.0)
+        };
+        renderer.play(&sound, &voice).unwrap();
+
+        // the attack takes a third of the note, which still falls to silence
+        let samples = left(&renderer);
+        assert_eq!(samples.len(), 60);
+        assert!((samples[20].abs() - FRAC_1_SQRT_2 as f32).abs() < 1e-6);
+        assert!(samples[59].abs() < 0.05);
+    }
+
+    #[test]
+    fn frequency_modulation() {
+        let mut plain = Renderer::default();
+        plain.play(&sound(Waveform::Sine), &voice(441.0)).unwrap();
+        let mut modulated = Renderer::default();
+        let sound = Sound {
+            fm_gain: 100.0,
+            fm_freq: 5.0,
+            ..sound(Waveform::Sine)
+        };
+        modulated.play(&sound, &voice(441.0)).unwrap();
+
+        assert_ne!(plain.samples(), modulated.samples());
+        // the modulation goes both ways, so the average frequency stays the same
+        let crossings = rising_crossings(&left(&modulated));
+        assert!((439..=442).contains(&crossings), "{}", crossings);
+    }
+
+    fn session() -> SoundGenerator<Renderer> {
+        let mut simulation = Conway::new();
+        for seed in 0..3 {
+            let mut grid = Grid::random_with(seed, DEFAULT_DENSITY);
+            grid.start();
+            simulation.add_game(grid);
+        }
+
+        let mut generator = SoundGenerator::with_backend(Renderer::default());
+        render_session(&mut simulation, 8, Strategy::PingPong, 0.25, &mut generator).unwrap();
+        generator
+    }
+
+    #[test]
+    fn deterministic_sessions() {
+        let first = session();
+        let second = session();
+        let renderer = first.backend();
+        assert!(renderer.now() >= 2.0);
+        assert!(renderer.samples().len() >= 2 * 2 * SAMPLE_RATE as usize);
+        assert!(renderer.samples().iter().any(|sample| sample.abs() > 0.01));
+        assert_eq!(renderer.samples(), second.backend().samples());
+    }
+
+    #[test]
+    fn session_to_file() {
+        let renderer = session().backend().clone();
+        let path = std::env::temp_dir().join(format!("session-{}.wav", std::process::id()));
+
+        for &format in &[SampleFormat::Int16, SampleFormat::Float32] {
+            let mut file = std::fs::File::create(&path).unwrap();
+            renderer.write_wav(&mut file, format).unwrap();
+            drop(file);
+
+            let mut expected = Vec::new();
+            renderer.write_wav(&mut expected, format).unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), expected);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
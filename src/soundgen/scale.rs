@@ -0,0 +1,346 @@
+//! Notes and the scales the generated notes are chosen from.
+
+use super::SoundError;
+use std::fmt;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// Pitch class of a note, the number of semitones above C
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub enum Note {
+    C = 0,
+    Db = 1,
+    D = 2,
+    Eb = 3,
+    E = 4,
+    F = 5,
+    Gb = 6,
+    G = 7,
+    Ab = 8,
+    A = 9,
+    Bb = 10,
+    B = 11,
+}
+
+impl Default for Note {
+    fn default() -> Self {
+        Note::C
+    }
+}
+
+impl Note {
+    /// Returns the note `semitones` above C, wrapping around the octave
+    pub fn from_semitones(semitones: u32) -> Note {
+        Note::iter().nth(semitones as usize % 12).unwrap()
+    }
+
+    pub fn semitones(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Parses the letter of a note followed by any number of flats (`b`) and sharps (`#`)
+impl FromStr for Note {
+    type Err = SoundError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || SoundError::UnknownNote(s.to_string());
+        let mut chars = s.chars();
+        let natural = match chars.next().ok_or_else(unknown)? {
+            'C' => Note::C,
+            'D' => Note::D,
+            'E' => Note::E,
+            'F' => Note::F,
+            'G' => Note::G,
+            'A' => Note::A,
+            'B' => Note::B,
+            _ => return Err(unknown()),
+        };
+        let semitones = chars.try_fold(natural.semitones() as i32, |semitones, c| match c {
+            'b' => Ok(semitones - 1),
+            '#' => Ok(semitones + 1),
+            _ => Err(unknown()),
+        })?;
+        Ok(Note::from_semitones(semitones.rem_euclid(12) as u32))
+    }
+}
+
+/// Scales of the catalogue, they can be played in any key
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, EnumString, Display)]
+pub enum ScaleKind {
+    Major,
+    NaturalMinor,
+    HarmonicMinor,
+    MelodicMinor,
+    Ionian,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Aeolian,
+    Locrian,
+    Pentatonic,
+    MinorPentatonic,
+    Blues,
+    WholeTone,
+    Chromatic,
+    /// Japanese pentatonic scales
+    Hirajoshi,
+    Insen,
+    Iwato,
+    /// Approximations of the Javanese scales, which don't fit the equal temperament
+    PelogSelisir,
+    PelogTembung,
+    Slendro,
+}
+
+impl Default for ScaleKind {
+    fn default() -> Self {
+        ScaleKind::Pentatonic
+    }
+}
+
+impl ScaleKind {
+    /// Semitones of the notes of the scale above its root
+    pub fn intervals(self) -> &'static [u8] {
+        use ScaleKind::*;
+
+        match self {
+            Major | Ionian => &[0, 2, 4, 5, 7, 9, 11],
+            NaturalMinor | Aeolian => &[0, 2, 3, 5, 7, 8, 10],
+            HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            MelodicMinor => &[0, 2, 3, 5, 7, 9, 11],
+            Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            Pentatonic => &[0, 2, 4, 7, 9],
+            MinorPentatonic => &[0, 3, 5, 7, 10],
+            Blues => &[0, 3, 5, 6, 7, 10],
+            WholeTone => &[0, 2, 4, 6, 8, 10],
+            Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Hirajoshi => &[0, 2, 3, 7, 8],
+            Insen => &[0, 1, 5, 7, 10],
+            Iwato => &[0, 1, 5, 6, 10],
+            PelogSelisir => &[0, 1, 3, 7, 8],
+            PelogTembung => &[0, 1, 5, 7, 8],
+            Slendro => &[0, 2, 5, 7, 9],
+        }
+    }
+
+    /// Returns the scale in the key of the `root`
+    pub fn scale(self, root: Note) -> Scale {
+        Scale {
+            root,
+            intervals: self.intervals().to_vec(),
+            in_degrees: false,
+        }
+    }
+}
+
+/// Notes of a scale in a single octave, counted in semitones from the root. The microtonal scales
+/// count them in the degrees of the tuning instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scale {
+    root: Note,
+    intervals: Vec<u8>,
+    /// Whether the intervals are degrees of the tuning rather than semitones
+    in_degrees: bool,
+}
+
+impl Scale {
+    pub fn pentatonic() -> Self {
+        ScaleKind::Pentatonic.scale(Note::C)
+    }
+
+    pub fn root(&self) -> Note {
+        self.root
+    }
+
+    /// Returns the same scale in the key of the `root`
+    pub fn transpose(&self, root: Note) -> Self {
+        Scale {
+            root,
+            intervals: self.intervals.clone(),
+            in_degrees: self.in_degrees,
+        }
+    }
+
+    pub fn in_degrees(&self) -> bool {
+        self.in_degrees
+    }
+
+    /// Interval from the root to the note at the `index` of the scale
+    pub fn interval(&self, index: usize) -> u8 {
+        self.intervals[index % self.len()]
+    }
+
+    /// Semitones of the note at the `index` of the scale above the C of the octave of the root
+    pub fn semitones(&self, index: usize) -> u8 {
+        self.root.semitones() + self.interval(index)
+    }
+
+    pub fn notes(&self) -> impl Iterator<Item = Note> + '_ {
+        (0..self.len()).map(move |index| Note::from_semitones(self.semitones(index) as u32))
+    }
+
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+}
+
+/// Prints the notes of the scale separated with spaces, the way they are parsed
+impl fmt::Display for Scale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let notes: Vec<String> = if self.in_degrees {
+            let degrees = self.intervals.iter().map(|degree| degree.to_string());
+            std::iter::once(self.root.to_string())
+                .chain(degrees)
+                .collect()
+        } else {
+            self.notes().map(|note| note.to_string()).collect()
+        };
+        write!(f, "{}", notes.join(" "))
+    }
+}
+
+/// Parses the notes of the scale separated with whitespace, like `C D Eb G Ab`. The first note is
+/// the root, the others are played in the ascending order above it. The root can also be followed
+/// by degrees of the tuning, like `C 0 4 7 10 14`, to play the microtones of the tunings dividing
+/// the octave into more than twelve degrees.
+impl FromStr for Scale {
+    type Err = SoundError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let root: Note = words.next().ok_or(SoundError::EmptyScale)?.parse()?;
+        let words: Vec<&str> = words.collect();
+
+        let degrees = words
+            .iter()
+            .map(|word| word.parse())
+            .collect::<Result<Vec<u8>, _>>();
+        let (mut intervals, in_degrees) = match degrees {
+            Ok(degrees) if !degrees.is_empty() => (degrees, true),
+            _ => {
+                let notes = words
+                    .iter()
+                    .map(|word| word.parse())
+                    .collect::<Result<Vec<Note>, _>>()?;
+                let intervals = notes
+                    .iter()
+                    .map(|note| (note.semitones() + 12 - root.semitones()) % 12)
+                    .collect();
+                (intervals, false)
+            }
+        };
+        intervals.push(0);
+        intervals.sort_unstable();
+        intervals.dedup();
+        Ok(Scale {
+            root,
+            intervals,
+            in_degrees,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn note_names() {
+        assert_eq!("Eb".parse::<Note>().unwrap(), Note::Eb);
+        assert_eq!("D#".parse::<Note>().unwrap(), Note::Eb);
+        assert_eq!("Cb".parse::<Note>().unwrap(), Note::B);
+        assert_eq!("B#".parse::<Note>().unwrap(), Note::C);
+        assert_eq!("Gbb".parse::<Note>().unwrap(), Note::F);
+        assert_eq!("Cbbbbbbbbbbbbb".parse::<Note>().unwrap(), Note::B);
+        assert!("H".parse::<Note>().is_err());
+        assert!("Cx".parse::<Note>().is_err());
+        assert!("".parse::<Note>().is_err());
+        for note in Note::iter() {
+            assert_eq!(note.to_string().parse::<Note>().unwrap(), note);
+        }
+    }
+
+    #[test]
+    fn catalogue() {
+        for kind in ScaleKind::iter() {
+            let intervals = kind.intervals();
+            assert_eq!(intervals[0], 0, "{}", kind);
+            assert!(
+                intervals.windows(2).all(|pair| pair[0] < pair[1]),
+                "{}",
+                kind
+            );
+            assert!(intervals.iter().all(|&interval| interval < 12), "{}", kind);
+            assert_eq!(kind.to_string().parse::<ScaleKind>().unwrap(), kind);
+        }
+        // the modes are rotations of the major scale
+        let major = ScaleKind::Major.intervals();
+        for (degree, mode) in [
+            ScaleKind::Ionian,
+            ScaleKind::Dorian,
+            ScaleKind::Phrygian,
+            ScaleKind::Lydian,
+            ScaleKind::Mixolydian,
+            ScaleKind::Aeolian,
+            ScaleKind::Locrian,
+        ]
+        .iter()
+        .enumerate()
+        {
+            let rotated: Vec<u8> = (0..7)
+                .map(|index| (major[(degree + index) % 7] + 12 - major[degree]) % 12)
+                .collect();
+            assert_eq!(mode.intervals(), rotated.as_slice(), "{}", mode);
+        }
+    }
+
+    #[test]
+    fn parsing_scales() {
+        let hirajoshi: Scale = "C D Eb G Ab".parse().unwrap();
+        assert_eq!(hirajoshi, ScaleKind::Hirajoshi.scale(Note::C));
+
+        let a_major: Scale = "A B C# D E F# G#".parse().unwrap();
+        assert_eq!(a_major, ScaleKind::Major.scale(Note::A));
+        assert_eq!(a_major.to_string(), "A B Db D E Gb Ab");
+        assert_eq!(a_major.semitones(2), 13);
+
+        // the notes are sorted and the repeated ones are dropped
+        let triad: Scale = " E  C G E ".parse().unwrap();
+        assert_eq!(triad.root(), Note::E);
+        assert_eq!(triad.to_string(), "E G C");
+
+        assert!("".parse::<Scale>().is_err());
+        assert!("C D X".parse::<Scale>().is_err());
+    }
+
+    #[test]
+    fn scales_in_degrees() {
+        let rast: Scale = "C 4 7 10 14 18 21".parse().unwrap();
+        assert!(rast.in_degrees());
+        assert_eq!(rast.len(), 7);
+        assert_eq!(rast.interval(2), 7);
+        assert_eq!(rast.to_string(), "C 0 4 7 10 14 18 21");
+        assert_eq!(rast.to_string().parse::<Scale>().unwrap(), rast);
+        assert!(rast.transpose(Note::D).in_degrees());
+
+        assert!(!"C".parse::<Scale>().unwrap().in_degrees());
+        assert!("C 4 E".parse::<Scale>().is_err());
+        assert!("C 4 300".parse::<Scale>().is_err());
+    }
+
+    #[test]
+    fn transposition() {
+        let scale = ScaleKind::Blues.scale(Note::C);
+        let transposed = scale.transpose(Note::G);
+        assert_eq!(transposed, ScaleKind::Blues.scale(Note::G));
+        assert_eq!(transposed.to_string().parse::<Scale>().unwrap(), transposed);
+        assert_eq!(scale.transpose(Note::C), scale);
+    }
+}
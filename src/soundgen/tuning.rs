@@ -0,0 +1,318 @@
+//! Tuning systems turning the notes into frequencies.
+
+use super::{Note, Result, SoundError};
+
+/// Frequency of the A above the middle C in the standard pitch
+pub const STANDARD_PITCH: f32 = 440.0;
+
+/// Midi note of the A above the middle C
+const A4: i32 = 69;
+
+/// Ratios of the notes of the 5-limit just intonation, from the tonic up to the major seventh
+const JUST_RATIOS: [f64; 12] = [
+    1.0,
+    16.0 / 15.0,
+    9.0 / 8.0,
+    6.0 / 5.0,
+    5.0 / 4.0,
+    4.0 / 3.0,
+    45.0 / 32.0,
+    3.0 / 2.0,
+    8.0 / 5.0,
+    5.0 / 3.0,
+    9.0 / 5.0,
+    15.0 / 8.0,
+];
+
+/// Pitches of the degrees of a scale repeating every period, usually an octave. The twelve notes
+/// of an octave are mapped onto the nearest degrees, counting from the tonic, so tunings with any
+/// number of degrees can play the scales. The scales counted in degrees reach all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning {
+    /// Ratios of the degrees to the tonic, starting with 1 for the tonic itself
+    ratios: Vec<f64>,
+    /// Ratio at which the degrees repeat
+    period: f64,
+    /// Frequency of the A above the middle C
+    reference: f32,
+}
+
+impl Tuning {
+    /// Equal temperament with `divisions` degrees in the octave, 12 for the usual tuning
+    pub fn equal(divisions: u32) -> Self {
+        let divisions = divisions.max(1);
+        Tuning {
+            ratios: (0..divisions)
+                .map(|degree| 2f64.powf(degree as f64 / divisions as f64))
+                .collect(),
+            period: 2.0,
+            reference: STANDARD_PITCH,
+        }
+    }
+
+    /// Just intonation with the ratios of the 5-limit
+    pub fn just() -> Self {
+        Tuning {
+            ratios: JUST_RATIOS.to_vec(),
+            period: 2.0,
+            reference: STANDARD_PITCH,
+        }
+    }
+
+    /// Parses the contents of a Scala `.scl` file. Pitches with a period are in cents, the others
+    /// are ratios, and the last pitch is the period of the scale.
+    pub fn from_scala(scl: &str) -> Result<Self> {
+        let invalid = |reason: &str| SoundError::InvalidScala(reason.to_string());
+        let mut lines = scl
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('!'));
+
+        // the description comes first, it may be empty
+        lines.next().ok_or_else(|| invalid("missing description"))?;
+        let count: usize = lines
+            .next()
+            .and_then(|line| line.split_whitespace().next())
+            .and_then(|count| count.parse().ok())
+            .ok_or_else(|| invalid("missing number of notes"))?;
+        if count == 0 {
+            return Err(invalid("no notes"));
+        }
+
+        let mut pitches = lines
+            .take(count)
+            .map(|line| {
+                let pitch = line.split_whitespace().next().unwrap_or_default();
+                parse_pitch(pitch).ok_or_else(|| invalid(&format!("invalid pitch: {}", pitch)))
+            })
+            .collect::<Result<Vec<f64>>>()?;
+        if pitches.len() < count {
+            return Err(invalid("missing notes"));
+        }
+
+        let period = pitches.pop().unwrap_or_default();
+        if period <= 1.0 {
+            return Err(invalid("period must be higher than the tonic"));
+        }
+        let mut ratios = vec![1.0];
+        ratios.extend(pitches);
+        Ok(Tuning {
+            ratios,
+            period,
+            reference: STANDARD_PITCH,
+        })
+    }
+
+    /// Returns the same tuning with the A above the middle C at the `reference` frequency
+    pub fn with_reference(self, reference: f32) -> Self {
+        Tuning { reference, ..self }
+    }
+
+    pub fn reference(&self) -> f32 {
+        self.reference
+    }
+
+    /// Number of degrees in the period
+    pub fn len(&self) -> usize {
+        self.ratios.len()
+    }
+
+    /// Frequency of the midi note in a scale starting at the `tonic`
+    pub fn frequency(&self, note: u8, tonic: Note) -> f32 {
+        let ratio = self.ratio(note as i32, tonic) / self.ratio(A4, tonic);
+        (self.reference as f64 * ratio) as f32
+    }
+
+    /// Frequency of the note `degrees` degrees above the `tonic` of the lowest midi octave
+    pub fn degree_frequency(&self, degrees: i32, tonic: Note) -> f32 {
+        let ratio = self.degree_ratio(degrees) / self.ratio(A4, tonic);
+        (self.reference as f64 * ratio) as f32
+    }
+
+    /// Nearest midi note of the frequency, midi files can't hold the microtones
+    pub fn midi_note(&self, frequency: f32) -> u8 {
+        let semitones = 12.0 * (frequency as f64 / self.reference as f64).log2();
+        (A4 as f64 + semitones).round().max(0.0).min(127.0) as u8
+    }
+
+    /// Ratio of the midi note to the tonic of the lowest octave
+    fn ratio(&self, note: i32, tonic: Note) -> f64 {
+        let semitones = note - tonic.semitones() as i32;
+        let (octave, semitone) = (semitones.div_euclid(12), semitones.rem_euclid(12));
+        let degree = (semitone as f64 * self.len() as f64 / 12.0).round() as i32;
+        self.degree_ratio(octave * self.len() as i32 + degree)
+    }
+
+    /// Ratio of the note `degrees` degrees above the tonic to the tonic
+    fn degree_ratio(&self, degrees: i32) -> f64 {
+        let len = self.len() as i32;
+        let (period, degree) = (degrees.div_euclid(len), degrees.rem_euclid(len));
+        self.period.powi(period) * self.ratios[degree as usize]
+    }
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning::equal(12)
+    }
+}
+
+/// Parses a pitch of a Scala file, either in cents like `701.955` or a ratio like `3/2` or `2`
+fn parse_pitch(pitch: &str) -> Option<f64> {
+    let ratio = if pitch.contains('.') {
+        2f64.powf(pitch.parse::<f64>().ok()? / 1200.0)
+    } else if let Some(slash) = pitch.find('/') {
+        let numerator: u64 = pitch[..slash].parse().ok()?;
+        let denominator: u64 = pitch[slash + 1..].parse().ok()?;
+        numerator as f64 / denominator as f64
+    } else {
+        pitch.parse::<u64>().ok()? as f64
+    };
+    Some(ratio).filter(|ratio| ratio.is_finite() && *ratio > 0.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn equal_temperament() {
+        let tuning = Tuning::default();
+        assert_close(tuning.frequency(69, Note::C), 440.0);
+        assert_close(tuning.frequency(21, Note::C), 27.5);
+        assert_close(tuning.frequency(60, Note::C), 261.63);
+        // the tonic doesn't matter for the equal temperaments
+        assert_close(tuning.frequency(60, Note::Gb), 261.63);
+
+        let tuning = Tuning::equal(12).with_reference(432.0);
+        assert_close(tuning.frequency(69, Note::C), 432.0);
+        assert_close(tuning.frequency(81, Note::C), 864.0);
+    }
+
+    #[test]
+    fn other_divisions_of_the_octave() {
+        // the major third of the meantone-like 19 divisions is six degrees
+        let tuning = Tuning::equal(19);
+        let third = tuning.frequency(64, Note::C) / tuning.frequency(60, Note::C);
+        assert_close(third, 2f32.powf(6.0 / 19.0));
+        assert_close(
+            tuning.frequency(72, Note::C),
+            2.0 * tuning.frequency(60, Note::C),
+        );
+
+        let tuning = Tuning::equal(31);
+        assert_eq!(tuning.len(), 31);
+        assert_close(tuning.frequency(69, Note::D), 440.0);
+    }
+
+    #[test]
+    fn quarter_tones() {
+        let tuning = Tuning::equal(24);
+        // the middle C is in the fifth octave above the lowest one
+        let c4 = tuning.degree_frequency(5 * 24, Note::C);
+        assert_close(c4, 261.63);
+        let quarter_tone = tuning.degree_frequency(5 * 24 + 1, Note::C);
+        assert_close(quarter_tone / c4, 2f32.powf(1.0 / 24.0));
+        assert!(c4 < quarter_tone && quarter_tone < tuning.frequency(61, Note::C));
+        assert_close(tuning.degree_frequency(5 * 24 + 2, Note::C), 277.18);
+        assert_eq!(
+            tuning.midi_note(tuning.degree_frequency(5 * 24 + 2, Note::C)),
+            61
+        );
+
+        // the degrees are counted from the tonic
+        let tuning = Tuning::just();
+        let fifth = tuning.degree_frequency(5 * 12 + 7, Note::D);
+        assert_close(fifth / tuning.degree_frequency(5 * 12, Note::D), 1.5);
+        assert_close(fifth, 440.0);
+        assert_eq!(tuning.midi_note(fifth), 69);
+    }
+
+    #[test]
+    fn just_intonation() {
+        let tuning = Tuning::just();
+        let fifth = tuning.frequency(67, Note::C) / tuning.frequency(60, Note::C);
+        assert_close(fifth, 1.5);
+        let third = tuning.frequency(64, Note::C) / tuning.frequency(60, Note::C);
+        assert_close(third, 1.25);
+        assert_close(tuning.frequency(69, Note::C), 440.0);
+
+        // the ratios are counted from the tonic of the scale
+        let fifth = tuning.frequency(74, Note::G) / tuning.frequency(67, Note::G);
+        assert_close(fifth, 1.5);
+    }
+
+    const MEANTONE: &str = "! meanquar.scl
+!
+1/4-comma meantone scale. Pietro Aaron's temperament (1523)
+ 12
+!
+ 76.04900
+ 193.15686
+ 310.26471
+ 5/4
+ 503.42157
+ 579.47057
+ 696.57843
+ 25/16
+ 889.73529
+ 1006.84314
+ 1082.89214
+ 2/1
+";
+
+    #[test]
+    fn scala_files() {
+        let tuning = Tuning::from_scala(MEANTONE).unwrap();
+        assert_eq!(tuning.len(), 12);
+        let third = tuning.frequency(64, Note::C) / tuning.frequency(60, Note::C);
+        assert_close(third, 1.25);
+        let fifth = tuning.frequency(67, Note::C) / tuning.frequency(60, Note::C);
+        assert_close(fifth, 2f64.powf(696.57843 / 1200.0) as f32);
+        assert_close(tuning.frequency(69, Note::C), 440.0);
+
+        // the degrees repeat every period, not every octave
+        let tritave = "Bohlen-Pierce\n2\n9/7\n3\n";
+        let tuning = Tuning::from_scala(tritave).unwrap();
+        assert_close(
+            tuning.frequency(72, Note::C) / tuning.frequency(60, Note::C),
+            3.0,
+        );
+
+        let empty_description = "\n1\n1200.0";
+        assert_eq!(
+            Tuning::from_scala(empty_description).unwrap(),
+            Tuning::equal(1)
+        );
+    }
+
+    #[test]
+    fn invalid_scala_files() {
+        for scl in &[
+            "",
+            "! only comments",
+            "description",
+            "description\nmany",
+            "description\n0\n",
+            "description\n3\n3/2\n2/1\n",
+            "description\n2\n3/0\n2/1\n",
+            "description\n2\n3/2\nfifth\n",
+            "description\n1\n-1200.0\n",
+            "description\n1\n1/2\n",
+        ] {
+            match Tuning::from_scala(scl) {
+                Err(SoundError::InvalidScala(_)) => {}
+                other => panic!("{:?} {:?}", scl, other),
+            }
+        }
+    }
+}
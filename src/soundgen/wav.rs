@@ -0,0 +1,171 @@
+//! Writer of the WAV files, either with 16-bit integer or 32-bit float samples.
+
+use super::{Result, SoundError};
+use std::convert::TryFrom;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleFormat {
+    Int16,
+    Float32,
+}
+
+impl Default for SampleFormat {
+    fn default() -> Self {
+        SampleFormat::Int16
+    }
+}
+
+impl SampleFormat {
+    fn bytes_per_sample(self) -> u16 {
+        match self {
+            SampleFormat::Int16 => 2,
+            SampleFormat::Float32 => 4,
+        }
+    }
+}
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+
+/// Writes the interleaved `samples` of the `channels` as a WAV file. The samples are expected
+/// to be between -1 and 1, louder ones are clipped in the integer format.
+pub fn write_wav<W: Write>(
+    writer: &mut W,
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+    format: SampleFormat,
+) -> Result<()> {
+    let bytes_per_sample = format.bytes_per_sample();
+    let block_align = channels * bytes_per_sample;
+
+    // the float files have the extended format chunk and the number of the frames in a fact chunk
+    let (format_tag, format_length, fact_length) = match format {
+        SampleFormat::Int16 => (WAVE_FORMAT_PCM, 16, 0),
+        SampleFormat::Float32 => (WAVE_FORMAT_IEEE_FLOAT, 18, 12),
+    };
+    let headers_length = 4 + (8 + format_length) + fact_length + 8;
+    let data_length =
+        data_length(samples.len(), bytes_per_sample, headers_length).ok_or(SoundError::TooLong)?;
+    let riff_length = headers_length + data_length;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&riff_length.to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&format_length.to_le_bytes())?;
+    writer.write_all(&format_tag.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&(bytes_per_sample * 8).to_le_bytes())?;
+    if format == SampleFormat::Float32 {
+        // no extension
+        writer.write_all(&0u16.to_le_bytes())?;
+        writer.write_all(b"fact")?;
+        writer.write_all(&4u32.to_le_bytes())?;
+        let frames = samples.len() as u32 / channels.max(1) as u32;
+        writer.write_all(&frames.to_le_bytes())?;
+    }
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_length.to_le_bytes())?;
+    for &sample in samples {
+        match format {
+            SampleFormat::Int16 => {
+                let sample = (sample.max(-1.0).min(1.0) * i16::MAX as f32).round() as i16;
+                writer.write_all(&sample.to_le_bytes())?;
+            }
+            SampleFormat::Float32 => writer.write_all(&sample.to_le_bytes())?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Length of the data chunk of the samples, none if the chunk and the `headers_length` bytes
+/// before it don't fit the 32-bit length of the RIFF chunk
+fn data_length(samples: usize, bytes_per_sample: u16, headers_length: u32) -> Option<u32> {
+    let data_length = samples.checked_mul(bytes_per_sample as usize)?;
+    let data_length = u32::try_from(data_length).ok()?;
+    data_length.checked_add(headers_length)?;
+    Some(data_length)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    #[test]
+    fn pcm_header() {
+        let mut file = Vec::new();
+        write_wav(
+            &mut file,
+            &[0.0, 1.0, -1.0, 2.0],
+            2,
+            44100,
+            SampleFormat::Int16,
+        )
+        .unwrap();
+
+        assert_eq!(file.len(), 44 + 8);
+        assert_eq!(&file[0..4], b"RIFF");
+        assert_eq!(u32_at(&file, 4) as usize, file.len() - 8);
+        assert_eq!(&file[8..16], b"WAVEfmt ");
+        assert_eq!(u16_at(&file, 20), WAVE_FORMAT_PCM);
+        assert_eq!(u16_at(&file, 22), 2);
+        assert_eq!(u32_at(&file, 24), 44100);
+        assert_eq!(u32_at(&file, 28), 44100 * 4);
+        assert_eq!(u16_at(&file, 32), 4);
+        assert_eq!(u16_at(&file, 34), 16);
+        assert_eq!(&file[36..40], b"data");
+        assert_eq!(u32_at(&file, 40), 8);
+
+        let samples: Vec<i16> = file[44..]
+            .chunks(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(samples, vec![0, i16::MAX, -i16::MAX, i16::MAX]);
+    }
+
+    #[test]
+    fn float_header() {
+        let mut file = Vec::new();
+        write_wav(&mut file, &[0.5, -0.25], 1, 48000, SampleFormat::Float32).unwrap();
+
+        assert_eq!(u32_at(&file, 4) as usize, file.len() - 8);
+        assert_eq!(u32_at(&file, 16), 18);
+        assert_eq!(u16_at(&file, 20), WAVE_FORMAT_IEEE_FLOAT);
+        assert_eq!(u16_at(&file, 34), 32);
+        assert_eq!(&file[38..42], b"fact");
+        assert_eq!(u32_at(&file, 46), 2);
+        assert_eq!(&file[50..54], b"data");
+        assert_eq!(u32_at(&file, 54), 8);
+        assert_eq!(
+            f32::from_le_bytes([file[58], file[59], file[60], file[61]]),
+            0.5
+        );
+    }
+
+    #[test]
+    fn too_long() {
+        assert_eq!(data_length(4, 2, 36), Some(8));
+        let samples = (u32::MAX / 4) as usize;
+        assert_eq!(data_length(samples, 2, 36), Some(u32::MAX / 4 * 2));
+        // the RIFF length counts the headers too
+        assert_eq!(data_length(samples, 4, 36), None);
+        assert_eq!(data_length(samples + 1, 4, 0), None);
+        assert_eq!(data_length(usize::MAX, 2, 36), None);
+    }
+}
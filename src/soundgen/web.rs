@@ -0,0 +1,84 @@
+//! Backend playing the sounds in the browser through the Web Audio API.
+
+use super::{Result, Sound, SynthBackend, Voice, Waveform};
+use web_sys::{AudioContext, OscillatorType};
+
+pub struct WebAudio {
+    ctx: AudioContext,
+}
+
+impl WebAudio {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            ctx: AudioContext::new()?,
+        })
+    }
+}
+
+impl From<Waveform> for OscillatorType {
+    fn from(waveform: Waveform) -> Self {
+        match waveform {
+            Waveform::Sine => OscillatorType::Sine,
+            Waveform::Square => OscillatorType::Square,
+            Waveform::Sawtooth => OscillatorType::Sawtooth,
+            Waveform::Triangle => OscillatorType::Triangle,
+        }
+    }
+}
+
+impl SynthBackend for WebAudio {
+    fn play(&mut self, sound: &Sound, voice: &Voice) -> Result<()> {
+        let start = self.ctx.current_time() + voice.offset.max(0.0);
+        let end = start + voice.duration;
+
+        // Create our web audio objects.
+        let primary = self.ctx.create_oscillator()?;
+        let fm_osc = self.ctx.create_oscillator()?;
+        let gain = self.ctx.create_gain()?;
+        let fm_gain = self.ctx.create_gain()?;
+        let panner = self.ctx.create_stereo_panner()?;
+
+        // Some initial settings:
+        primary.set_type(sound.waveform.into());
+        primary.frequency().set_value(voice.frequency);
+
+        // Give the amp a shape.
+        gain.gain().set_value_at_time(0.0, start)?;
+        gain.gain()
+            .linear_ramp_to_value_at_time(voice.peak, start + sound.attack_end(voice.duration))?;
+        gain.gain()
+            .linear_ramp_to_value_at_time(0.0, start + sound.release_start(voice.duration))?;
+
+        fm_gain.gain().set_value(sound.fm_gain);
+        fm_osc.set_type(OscillatorType::Sine);
+        fm_osc.frequency().set_value(sound.fm_freq);
+
+        // Connect the nodes up!
+
+        // The primary oscillator is routed through the gain node, so that
+        // it can control the overall output volume.
+        primary.connect_with_audio_node(&gain)?;
+
+        // Then connect the gain node through the panner to the AudioContext
+        // destination (aka your speakers).
+        panner.pan().set_value(voice.pan.max(-1.0).min(1.0));
+        gain.connect_with_audio_node(&panner)?;
+        panner.connect_with_audio_node(&self.ctx.destination())?;
+
+        // The FM oscillator is connected to its own gain node, so it can
+        // control the amount of modulation.
+        fm_osc.connect_with_audio_node(&fm_gain)?;
+
+        // Connect the FM oscillator to the frequency parameter of the main
+        // oscillator, so that the FM node can modulate its frequency.
+        fm_gain.connect_with_audio_param(&primary.frequency())?;
+
+        // Start the oscillators!
+        primary.start_with_when(start)?;
+        primary.stop_with_when(end)?;
+        fm_osc.start_with_when(start)?;
+        fm_osc.stop_with_when(end)?;
+
+        Ok(())
+    }
+}